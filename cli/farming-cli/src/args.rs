@@ -73,11 +73,61 @@ pub enum CliCommand {
         // reward_b_mint: Pubkey,
         reward_duration: u64,
     },
+    /// Initialize the program-wide config, the wallet must be the program upgrade authority and becomes the program admin
+    InitGlobalConfig {
+        #[clap(long, default_value_t = 0)]
+        default_fee_bps: u16,
+        #[clap(long)]
+        fee_recipient: Pubkey,
+    },
+    /// Program admin updates the program admin and the default fee settings
+    UpdateGlobalConfig {
+        #[clap(long)]
+        admin: Pubkey,
+        #[clap(long)]
+        default_fee_bps: u16,
+        #[clap(long)]
+        fee_recipient: Pubkey,
+    },
+    /// Program admin adds a staking mint to the allowlist
+    AllowStakingMint {
+        #[clap(long)]
+        mint: Pubkey,
+    },
+    /// Program admin removes a staking mint from the allowlist
+    DisallowStakingMint {
+        #[clap(long)]
+        mint: Pubkey,
+    },
+    /// List pools of a staking mint
+    ListPools {
+        #[clap(long)]
+        staking_mint: Pubkey,
+    },
+    /// Admin sets the pool name, symbol, URI and tags
+    SetPoolMetadata {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        name: String,
+        #[clap(long)]
+        symbol: String,
+        #[clap(long, default_value_t = String::from(""))]
+        uri: String,
+        /// Repeat to set several tags
+        #[clap(long = "tag")]
+        tags: Vec<String>,
+    },
     /// User enables staking
     CreateUser {
         #[clap(long)]
         pool: Pubkey,
     },
+    /// Anyone advances the pool reward, points and epoch accounting to now
+    Checkpoint {
+        #[clap(long)]
+        pool: Pubkey,
+    },
     /// Admin pauses the pool
     Pause {
         #[clap(long)]
@@ -88,13 +138,106 @@ pub enum CliCommand {
         #[clap(long)]
         pool: Pubkey,
     },
-    /// User stakes
+    /// Admin sets how long deposits must stay staked before they earn rewards
+    SetMinStakeSeconds {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        min_stake_seconds: u64,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin sets the penalty paid by withdrawals shortly after a deposit
+    SetEarlyExitPenalty {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        penalty_bps: u16,
+        #[clap(long)]
+        window_seconds: u64,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin sets how much of the stake may be withdrawn per window before withdrawals pause
+    SetOutflowLimit {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        limit_bps: u16,
+        #[clap(long)]
+        window_seconds: u64,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin sets the curve mapping the pool total staked to its emission rate
+    SetUtilizationCurve {
+        #[clap(long)]
+        pool: Pubkey,
+        /// `<TOTAL_STAKED>:<RATE>` in UI units with the rate per second scaled by 1e9, repeat by increasing total staked.
+        /// Leave out to remove the curve.
+        #[clap(long = "point")]
+        points: Vec<String>,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin sets the points earned per staked token per second
+    SetPointsRate {
+        #[clap(long)]
+        pool: Pubkey,
+        /// Points per staked token per second, scaled by 1e9
+        #[clap(long)]
+        points_per_token_second: u128,
+        /// Stop crediting token rewards
+        #[clap(long)]
+        points_only: bool,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin sets the delay between queueing and executing admin actions
+    SetTimelockSeconds {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        timelock_seconds: u64,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin cancels a queued admin action
+    CancelAction {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        action_id: u64,
+    },
+    /// Admin sets the per-operation pause flags
+    SetPauseFlags {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        deposit: bool,
+        #[clap(long)]
+        withdraw: bool,
+        #[clap(long)]
+        claim: bool,
+        #[clap(long)]
+        fund: bool,
+    },
+    /// User stakes, creating the user stake account if it does not exist yet
     Deposit {
         #[clap(long)]
         pool: Pubkey,
         #[clap(long)]
         amount: u64,
     },
+    /// Payer stakes on behalf of a beneficiary wallet
+    DepositFor {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        beneficiary: Pubkey,
+        #[clap(long)]
+        amount: u64,
+    },
     /// User unstakes
     Withdraw {
         #[clap(long)]
@@ -108,6 +251,8 @@ pub enum CliCommand {
         pool: Pubkey,
         #[clap(long)]
         funder: Pubkey,
+        #[clap(long)]
+        action_id: Option<u64>,
     },
     /// Admin removes a wallet as funder
     Deauthorize {
@@ -115,6 +260,8 @@ pub enum CliCommand {
         pool: Pubkey,
         #[clap(long)]
         funder: Pubkey,
+        #[clap(long)]
+        action_id: Option<u64>,
     },
     /// Admin or funder funds rewards to pool
     Fund {
@@ -124,21 +271,118 @@ pub enum CliCommand {
         amount_a: u64,
         // #[clap(long)]
         // amount_b: u64,
+        /// Unix timestamp at which the reward period starts, defaults to now
+        #[clap(long)]
+        start_time: Option<u64>,
+    },
+    /// Admin or funder starts the next epoch and funds its budget
+    StartEpoch {
+        #[clap(long)]
+        pool: Pubkey,
+        /// Unix timestamp at which the epoch starts, not in the past
+        #[clap(long)]
+        start_time: u64,
+        /// Unix timestamp at which the epoch ends
+        #[clap(long)]
+        end_time: u64,
+        /// Reward A tokens shared by the stakers of the epoch, in base units
+        #[clap(long)]
+        budget: u64,
+    },
+    /// Finalize the current epoch once it has ended, opening its claims
+    FinalizeEpoch {
+        #[clap(long)]
+        pool: Pubkey,
+    },
+    /// Anyone releases the unclaimed budget of an epoch whose claim period is over
+    SweepEpoch {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        epoch: u64,
+    },
+    /// Admin appends a future segment to the emission schedule
+    AppendEmissionSegment {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        start_time: u64,
+        /// Reward per second in UI units, scaled by 1e9
+        #[clap(long)]
+        rate: u128,
+        #[clap(long, default_value_t = 0)]
+        halving_interval: u64,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin changes the length of reward periods
+    SetRewardDuration {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        duration: u64,
+        #[clap(long)]
+        action_id: Option<u64>,
     },
     /// User claims pending rewards
     Claim {
         #[clap(long)]
         pool: Pubkey,
     },
-    /// Admin closes a user stake account
+    /// User claims pending rewards from several pools in one transaction
+    ClaimMany {
+        /// Repeat for each pool
+        #[clap(long = "pool", required = true)]
+        pools: Vec<Pubkey>,
+    },
+    /// User claims their share of a finalized epoch budget
+    ClaimEpoch {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        epoch: u64,
+    },
+    /// Admin withdraws reward tokens no user is owed after the farm ends
+    WithdrawUnallocatedRewards {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// User closes their empty stake account
     CloseUser {
         #[clap(long)]
         pool: Pubkey,
     },
+    /// Admin closes an abandoned user stake account after the grace period
+    ForceCloseUser {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        owner: Pubkey,
+        #[clap(long)]
+        action_id: Option<u64>,
+    },
+    /// Admin freezes a user, blocking their deposits and claims
+    FreezeUser {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        owner: Pubkey,
+    },
+    /// Admin unfreezes a frozen user
+    UnfreezeUser {
+        #[clap(long)]
+        pool: Pubkey,
+        #[clap(long)]
+        owner: Pubkey,
+    },
     /// Admin closes the pool
     ClosePool {
         #[clap(long)]
         pool: Pubkey,
+        #[clap(long)]
+        action_id: Option<u64>,
     },
     /// Show pool info
     ShowInfo {
@@ -152,7 +396,9 @@ pub enum CliCommand {
     },
 
     CheckFunderAllPool {},
-    MigrateFarmingRate {},
+    /// Migrate all pool and user accounts to the latest account version
+    #[clap(alias = "migrate-farming-rate")]
+    Migrate {},
 }
 
 #[derive(Parser, Debug)]
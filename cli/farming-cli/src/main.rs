@@ -3,6 +3,8 @@ mod utils;
 
 use crate::args::*;
 use crate::utils::*;
+use anchor_client::anchor_lang::AccountDeserialize;
+use anchor_client::anchor_lang::Discriminator;
 use anchor_client::anchor_lang::InstructionData;
 use anchor_client::anchor_lang::ToAccountMetas;
 use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
@@ -15,8 +17,9 @@ use anchor_spl::token::spl_token;
 use anyhow::Ok;
 use anyhow::Result;
 use clap::*;
-use farming::Pool;
-use solana_program::instruction::Instruction;
+use farming::{AdminAction, Pool, QueuedAction, User};
+use solana_program::bpf_loader_upgradeable::get_program_data_address;
+use solana_program::instruction::{AccountMeta, Instruction};
 use std::ops::Deref;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -58,42 +61,276 @@ fn main() -> Result<()> {
                 reward_duration,
             )?;
         }
+        CliCommand::InitGlobalConfig {
+            default_fee_bps,
+            fee_recipient,
+        } => {
+            init_global_config(&program, priority_fee, &payer, default_fee_bps, fee_recipient)?;
+        }
+        CliCommand::UpdateGlobalConfig {
+            admin,
+            default_fee_bps,
+            fee_recipient,
+        } => {
+            update_global_config(
+                &program,
+                priority_fee,
+                &payer,
+                admin,
+                default_fee_bps,
+                fee_recipient,
+            )?;
+        }
+        CliCommand::AllowStakingMint { mint } => {
+            allow_staking_mint(&program, priority_fee, &payer, mint)?;
+        }
+        CliCommand::DisallowStakingMint { mint } => {
+            disallow_staking_mint(&program, priority_fee, &payer, mint)?;
+        }
+        CliCommand::ListPools { staking_mint } => {
+            list_pools(&program, &staking_mint)?;
+        }
+        CliCommand::SetPoolMetadata {
+            pool,
+            name,
+            symbol,
+            uri,
+            tags,
+        } => {
+            set_pool_metadata(&program, priority_fee, &payer, &pool, name, symbol, uri, tags)?;
+        }
         CliCommand::CreateUser { pool } => {
             create_user(&program, priority_fee, &payer, &pool)?;
         }
+        CliCommand::Checkpoint { pool } => {
+            checkpoint(&program, priority_fee, &payer, &pool)?;
+        }
         CliCommand::Pause { pool } => {
             pause(&program, priority_fee, &payer, &pool)?;
         }
         CliCommand::Unpause { pool } => {
             unpause(&program, priority_fee, &payer, &pool)?;
         }
+        CliCommand::SetPauseFlags {
+            pool,
+            deposit,
+            withdraw,
+            claim,
+            fund,
+        } => {
+            let mut pause_flags = 0;
+            if deposit {
+                pause_flags |= farming::PAUSE_DEPOSIT;
+            }
+            if withdraw {
+                pause_flags |= farming::PAUSE_WITHDRAW;
+            }
+            if claim {
+                pause_flags |= farming::PAUSE_CLAIM;
+            }
+            if fund {
+                pause_flags |= farming::PAUSE_FUND;
+            }
+            set_pause_flags(&program, priority_fee, &payer, &pool, pause_flags)?;
+        }
+        CliCommand::SetMinStakeSeconds {
+            pool,
+            min_stake_seconds,
+            action_id,
+        } => {
+            set_min_stake_seconds(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                min_stake_seconds,
+                action_id,
+            )?;
+        }
+        CliCommand::SetEarlyExitPenalty {
+            pool,
+            penalty_bps,
+            window_seconds,
+            action_id,
+        } => {
+            set_early_exit_penalty(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                penalty_bps,
+                window_seconds,
+                action_id,
+            )?;
+        }
+        CliCommand::SetOutflowLimit {
+            pool,
+            limit_bps,
+            window_seconds,
+            action_id,
+        } => {
+            set_outflow_limit(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                limit_bps,
+                window_seconds,
+                action_id,
+            )?;
+        }
+        CliCommand::SetUtilizationCurve {
+            pool,
+            points,
+            action_id,
+        } => {
+            set_utilization_curve(&program, priority_fee, &payer, &pool, &points, action_id)?;
+        }
+        CliCommand::SetPointsRate {
+            pool,
+            points_per_token_second,
+            points_only,
+            action_id,
+        } => {
+            set_points_rate(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                points_per_token_second,
+                points_only,
+                action_id,
+            )?;
+        }
+        CliCommand::SetTimelockSeconds {
+            pool,
+            timelock_seconds,
+            action_id,
+        } => {
+            set_timelock_seconds(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                timelock_seconds,
+                action_id,
+            )?;
+        }
+        CliCommand::CancelAction { pool, action_id } => {
+            cancel_action(&program, priority_fee, &payer, &pool, action_id)?;
+        }
         CliCommand::Deposit { pool, amount } => {
             stake(&program, priority_fee, &payer, &pool, amount)?;
         }
+        CliCommand::DepositFor {
+            pool,
+            beneficiary,
+            amount,
+        } => {
+            deposit_for(&program, priority_fee, &payer, &pool, &beneficiary, amount)?;
+        }
         CliCommand::Withdraw { pool, spt_amount } => {
             unstake(&program, priority_fee, &payer, &pool, spt_amount)?;
         }
-        CliCommand::Authorize { pool, funder } => {
-            authorize_funder(&program, priority_fee, &payer, &pool, &funder)?;
+        CliCommand::Authorize {
+            pool,
+            funder,
+            action_id,
+        } => {
+            authorize_funder(&program, priority_fee, &payer, &pool, &funder, action_id)?;
         }
-        CliCommand::Deauthorize { pool, funder } => {
-            deauthorize_funder(&program, priority_fee, &payer, &pool, &funder)?;
+        CliCommand::Deauthorize {
+            pool,
+            funder,
+            action_id,
+        } => {
+            deauthorize_funder(&program, priority_fee, &payer, &pool, &funder, action_id)?;
         }
         CliCommand::Fund {
             pool,
             amount_a,
             // amount_b,
+            start_time,
         } => {
-            fund(&program, priority_fee, &payer, &pool, amount_a)?;
+            fund(&program, priority_fee, &payer, &pool, amount_a, start_time)?;
+        }
+        CliCommand::StartEpoch {
+            pool,
+            start_time,
+            end_time,
+            budget,
+        } => {
+            start_epoch(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                start_time,
+                end_time,
+                budget,
+            )?;
+        }
+        CliCommand::FinalizeEpoch { pool } => {
+            finalize_epoch(&program, priority_fee, &payer, &pool)?;
+        }
+        CliCommand::SweepEpoch { pool, epoch } => {
+            sweep_epoch(&program, priority_fee, &payer, &pool, epoch)?;
+        }
+        CliCommand::AppendEmissionSegment {
+            pool,
+            start_time,
+            rate,
+            halving_interval,
+            action_id,
+        } => {
+            append_emission_segment(
+                &program,
+                priority_fee,
+                &payer,
+                &pool,
+                start_time,
+                rate,
+                halving_interval,
+                action_id,
+            )?;
+        }
+        CliCommand::SetRewardDuration {
+            pool,
+            duration,
+            action_id,
+        } => {
+            set_reward_duration(&program, priority_fee, &payer, &pool, duration, action_id)?;
         }
         CliCommand::Claim { pool } => {
             claim(&program, priority_fee, &payer, &pool)?;
         }
+        CliCommand::ClaimMany { pools } => {
+            claim_many(&program, priority_fee, &payer, &pools)?;
+        }
+        CliCommand::ClaimEpoch { pool, epoch } => {
+            claim_epoch(&program, priority_fee, &payer, &pool, epoch)?;
+        }
+        CliCommand::WithdrawUnallocatedRewards { pool, action_id } => {
+            withdraw_unallocated_rewards(&program, priority_fee, &payer, &pool, action_id)?;
+        }
         CliCommand::CloseUser { pool } => {
             close_user(&program, priority_fee, &payer, &pool)?;
         }
-        CliCommand::ClosePool { pool } => {
-            close_pool(&program, priority_fee, &payer, &pool)?;
+        CliCommand::ForceCloseUser {
+            pool,
+            owner,
+            action_id,
+        } => {
+            force_close_user(&program, priority_fee, &payer, &pool, &owner, action_id)?;
+        }
+        CliCommand::FreezeUser { pool, owner } => {
+            set_user_frozen(&program, priority_fee, &payer, &pool, &owner, true)?;
+        }
+        CliCommand::UnfreezeUser { pool, owner } => {
+            set_user_frozen(&program, priority_fee, &payer, &pool, &owner, false)?;
+        }
+        CliCommand::ClosePool { pool, action_id } => {
+            close_pool(&program, priority_fee, &payer, &pool, action_id)?;
         }
         CliCommand::ShowInfo { pool } => {
             show_info(&program, &pool)?;
@@ -104,45 +341,1182 @@ fn main() -> Result<()> {
         CliCommand::CheckFunderAllPool {} => {
             check_funder_all_pool(&program)?;
         }
-        CliCommand::MigrateFarmingRate {} => {
-            migrate_farming_rate(&program)?;
+        CliCommand::Migrate {} => {
+            migrate(&program, priority_fee, &payer)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn initialize_pool<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    base_location: String,
+    authority: &Keypair,
+    staking_mint: &Pubkey,
+    reward_a_mint: &Pubkey,
+    // reward_b_mint: &Pubkey,
+    reward_duration: u64,
+) -> Result<()> {
+    let base_keypair = read_keypair_file(base_location).expect("base keypair file not found");
+    let base_pubkey = base_keypair.pubkey();
+    let pool_pda = get_pool_pda(
+        &program,
+        reward_duration,
+        &staking_mint,
+        reward_a_mint,
+        // reward_b_mint,
+        &base_pubkey,
+    )?;
+
+    println!("pool address {}", pool_pda.pubkey);
+
+    let VaultPDAs {
+        staking_vault,
+        reward_a_vault,
+        // reward_b_vault,
+    } = get_vault_pdas(&program.id(), &pool_pda.pubkey);
+    let (staking_vault_pubkey, _) = staking_vault;
+    let (reward_a_vault_pubkey, _) = reward_a_vault;
+    // let (reward_b_vault_pubkey, _) = reward_b_vault;
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::InitializePool {
+            pool: pool_pda.pubkey,
+            staking_mint: *staking_mint,
+            staking_vault: staking_vault_pubkey,
+            reward_a_mint: *reward_a_mint,
+            reward_a_vault: reward_a_vault_pubkey,
+            // reward_b_mint: *reward_b_mint,
+            // reward_b_vault: reward_b_vault_pubkey,
+            global_config: get_global_config_pda(&program.id()),
+            pool_index: get_pool_index_pda(staking_mint, &program.id()),
+            authority: authority.pubkey(),
+            base: base_pubkey,
+            system_program: solana_program::system_program::ID,
+            token_program: spl_token::ID,
+            rent: solana_program::sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::InitializePool { reward_duration }.data(),
+    });
+
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority).signer(&base_keypair);
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn init_global_config<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    admin: &Keypair,
+    default_fee_bps: u16,
+    fee_recipient: Pubkey,
+) -> Result<()> {
+    let global_config = get_global_config_pda(&program.id());
+    println!("global config address {}", global_config);
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::InitializeGlobalConfig {
+            global_config,
+            admin: admin.pubkey(),
+            program: program.id(),
+            program_data: get_program_data_address(&program.id()),
+            system_program: solana_program::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::InitializeGlobalConfig {
+            default_fee_bps,
+            fee_recipient,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(admin);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn update_global_config<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    admin: &Keypair,
+    new_admin: Pubkey,
+    default_fee_bps: u16,
+    fee_recipient: Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::GlobalConfigChange {
+            global_config: get_global_config_pda(&program.id()),
+            admin: admin.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::UpdateGlobalConfig {
+            admin: new_admin,
+            default_fee_bps,
+            fee_recipient,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(admin);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn allow_staking_mint<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    admin: &Keypair,
+    mint: Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::GlobalConfigChange {
+            global_config: get_global_config_pda(&program.id()),
+            admin: admin.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::AllowStakingMint { mint }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(admin);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn disallow_staking_mint<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    admin: &Keypair,
+    mint: Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::GlobalConfigChange {
+            global_config: get_global_config_pda(&program.id()),
+            admin: admin.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::DisallowStakingMint { mint }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(admin);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn list_pools<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    staking_mint: &Pubkey,
+) -> Result<()> {
+    let pool_index: farming::PoolIndex =
+        program.account(get_pool_index_pda(staking_mint, &program.id()))?;
+    for pool in pool_index
+        .pools
+        .iter()
+        .filter(|pool| **pool != Pubkey::default())
+    {
+        println!("{}", pool);
+    }
+    Ok(())
+}
+
+pub fn set_pool_metadata<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    tags: Vec<String>,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetPoolMetadata {
+            pool: *pool,
+            pool_metadata: get_pool_metadata_pda(pool, &program.id()),
+            authority: authority.pubkey(),
+            system_program: solana_program::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetPoolMetadata {
+            name,
+            symbol,
+            uri,
+            tags,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn create_user<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    owner: &Keypair,
+    pool: &Pubkey,
+) -> Result<()> {
+    let UserPDA { user } = get_user_pda(pool, &owner.pubkey(), &program.id());
+    let (user_pubkey, _) = user;
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::CreateUser {
+            pool: *pool,
+            user: user_pubkey,
+            owner: owner.pubkey(),
+            system_program: solana_program::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::CreateUser {}.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(owner);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn checkpoint<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    caller: &Keypair,
+    pool: &Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::Checkpoint {
+            pool: *pool,
+            caller: caller.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::Checkpoint {}.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(caller);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn pause<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::Pause {
+            pool: *pool,
+            authority: authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::Pause {}.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn unpause<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::Unpause {
+            pool: *pool,
+            authority: authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::Unpause {}.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_pause_flags<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    pause_flags: u8,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetPauseFlags {
+            pool: *pool,
+            authority: authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetPauseFlags { pause_flags }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_min_stake_seconds<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    min_stake_seconds: u64,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetMinStakeSeconds { min_stake_seconds },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetMinStakeSeconds {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetMinStakeSeconds { min_stake_seconds }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_early_exit_penalty<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    penalty_bps: u16,
+    window_seconds: u64,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetEarlyExitPenalty {
+            penalty_bps,
+            window_seconds,
+        },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetEarlyExitPenalty {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetEarlyExitPenalty {
+            penalty_bps,
+            window_seconds,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_outflow_limit<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    limit_bps: u16,
+    window_seconds: u64,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetOutflowLimit {
+            limit_bps,
+            window_seconds,
+        },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetOutflowLimit {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetOutflowLimit {
+            limit_bps,
+            window_seconds,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_utilization_curve<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    points: &[String],
+    action_id: Option<u64>,
+) -> Result<()> {
+    if points.len() > farming::MAX_CURVE_POINTS {
+        anyhow::bail!("at most {} curve points", farming::MAX_CURVE_POINTS);
+    }
+    let mut curve = farming::UtilizationCurve::default();
+    for (slot, point) in curve.points.iter_mut().zip(points) {
+        let (total_staked, rate) = point
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("curve point {} is not <TOTAL_STAKED>:<RATE>", point))?;
+        *slot = farming::CurvePoint {
+            total_staked: total_staked.parse()?,
+            rate: rate.parse()?,
+        };
+    }
+    curve.len = points.len() as u8;
+
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetUtilizationCurve { curve },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetUtilizationCurve {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetUtilizationCurve { curve }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_points_rate<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    points_per_token_second: u128,
+    points_only: bool,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetPointsRate {
+            points_per_token_second,
+            points_only,
+        },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetPointsRate {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetPointsRate {
+            points_per_token_second,
+            points_only,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn set_timelock_seconds<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    timelock_seconds: u64,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetTimelockSeconds { timelock_seconds },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SetTimelockSeconds {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SetTimelockSeconds { timelock_seconds }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+/// Outcome of [timelock_step]
+pub enum TimelockStep {
+    /// The action has just been queued, nothing else to send
+    Queued,
+    /// Send the action, with the queued action account if any
+    Execute(Option<Pubkey>),
+}
+
+/// Without `action_id` the action is sent as is. Otherwise the first run queues the action
+/// under `action_id` and the next runs execute it with the queued action account.
+pub fn timelock_step<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    action_id: Option<u64>,
+    action: AdminAction,
+) -> Result<TimelockStep> {
+    let action_id = match action_id {
+        Some(action_id) => action_id,
+        None => return Ok(TimelockStep::Execute(None)),
+    };
+    let queued_action = get_queued_action_pda(pool, action_id, &program.id());
+    if let Some(queued) = program.account::<QueuedAction>(queued_action).ok() {
+        println!("Executing queued action {} eta {}", queued_action, queued.eta);
+        return Ok(TimelockStep::Execute(Some(queued_action)));
+    }
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::QueueAction {
+            pool: *pool,
+            queued_action,
+            authority: authority.pubkey(),
+            system_program: solana_program::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::QueueAction { action_id, action }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    let queued: QueuedAction = program.account(queued_action)?;
+    println!("Queued action {} eta {}", queued_action, queued.eta);
+    Ok(TimelockStep::Queued)
+}
+
+pub fn cancel_action<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    action_id: u64,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::CancelAction {
+            pool: *pool,
+            queued_action: get_queued_action_pda(pool, action_id, &program.id()),
+            authority: authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::CancelAction {}.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn stake<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    owner: &Keypair,
+    pool_pda: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let pool = get_pool(program, *pool_pda)?;
+    let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
+    let (user_pubkey, _) = user;
+
+    let stake_from_account = get_or_create_ata(&program, &owner.pubkey(), &pool.staking_mint)?;
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    // Create the user staking account along with the first deposit.
+    if get_user(program, user_pubkey).is_err() {
+        instructions.push(Instruction {
+            program_id: program.id(),
+            accounts: farming::accounts::DepositWithInit {
+                pool: *pool_pda,
+                staking_vault: pool.staking_vault,
+                stake_from_account,
+                user: user_pubkey,
+                owner: owner.pubkey(),
+                token_program: spl_token::ID,
+                system_program: solana_program::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: farming::instruction::DepositWithInit { amount }.data(),
+        });
+    } else {
+        instructions.push(Instruction {
+            program_id: program.id(),
+            accounts: farming::accounts::Deposit {
+                pool: *pool_pda,
+                staking_vault: pool.staking_vault,
+                stake_from_account,
+                user: user_pubkey,
+                owner: owner.pubkey(),
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: farming::instruction::Deposit { amount }.data(),
+        });
+    }
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(owner);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+
+    Ok(())
+}
+
+pub fn deposit_for<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    beneficiary: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let pool = get_pool(program, *pool_pda)?;
+    let UserPDA { user } = get_user_pda(pool_pda, beneficiary, &program.id());
+    let (user_pubkey, _) = user;
+    let UserPDA { user: payer_user } = get_user_pda(pool_pda, &payer.pubkey(), &program.id());
+    let (payer_user, _) = payer_user;
+
+    let stake_from_account = get_or_create_ata(&program, &payer.pubkey(), &pool.staking_mint)?;
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::DepositFor {
+            pool: *pool_pda,
+            staking_vault: pool.staking_vault,
+            stake_from_account,
+            user: user_pubkey,
+            beneficiary: *beneficiary,
+            payer: payer.pubkey(),
+            payer_user,
+            token_program: spl_token::ID,
+            system_program: solana_program::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::DepositFor { amount }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(payer);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+
+    Ok(())
+}
+
+pub fn unstake<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    owner: &Keypair,
+    pool_pda: &Pubkey,
+    spt_amount: u64,
+) -> Result<()> {
+    let pool = get_pool(program, *pool_pda)?;
+    let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
+    let (user_pubkey, _) = user;
+    let stake_from_account = get_or_create_ata(&program, &owner.pubkey(), &pool.staking_mint)?;
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::Withdraw {
+            pool: *pool_pda,
+            staking_vault: pool.staking_vault,
+            stake_from_account,
+            user: user_pubkey,
+            owner: owner.pubkey(),
+            token_program: spl_token::ID,
+            reward_a_vault: Some(pool.reward_a_vault),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::Withdraw { spt_amount }.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(owner);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+
+    Ok(())
+}
+
+pub fn authorize_funder<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    funder_to_add: &Pubkey,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::AuthorizeFunder {
+            funder: *funder_to_add,
+        },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::FunderChange {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::AuthorizeFunder {
+            funder_to_add: *funder_to_add,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn deauthorize_funder<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    authority: &Keypair,
+    pool: &Pubkey,
+    funder_to_remove: &Pubkey,
+    action_id: Option<u64>,
+) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::DeauthorizeFunder {
+            funder: *funder_to_remove,
+        },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::FunderChange {
+            pool: *pool,
+            authority: authority.pubkey(),
+            queued_action,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::DeauthorizeFunder {
+            funder_to_remove: *funder_to_remove,
+        }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(authority);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn fund<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    funder: &Keypair,
+    pool_pda: &Pubkey,
+    amount_a: u64,
+    // amount_b: u64,
+    start_time: Option<u64>,
+) -> Result<()> {
+    let pool = get_pool(program, *pool_pda)?;
+    let from_a = get_or_create_ata(&program, &funder.pubkey(), &pool.reward_a_mint)?;
+    // let from_b = get_or_create_ata(&program, &funder.pubkey(), &pool.reward_b_mint)?;
+
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::Fund {
+            pool: *pool_pda,
+            staking_vault: pool.staking_vault,
+            reward_a_vault: pool.reward_a_vault,
+            // reward_b_vault: pool.reward_b_vault,
+            funder: funder.pubkey(),
+            from_a,
+            // from_b,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: farming::instruction::Fund {
+            amount_a,
+            start_time,
         }
-    }
+        .data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(funder);
 
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
     Ok(())
 }
 
-fn initialize_pool<C: Deref<Target = impl Signer> + Clone>(
+pub fn start_epoch<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
-    base_location: String,
-    authority: &Keypair,
-    staking_mint: &Pubkey,
-    reward_a_mint: &Pubkey,
-    // reward_b_mint: &Pubkey,
-    reward_duration: u64,
+    funder: &Keypair,
+    pool_pda: &Pubkey,
+    start_time: u64,
+    end_time: u64,
+    budget: u64,
 ) -> Result<()> {
-    let base_keypair = read_keypair_file(base_location).expect("base keypair file not found");
-    let base_pubkey = base_keypair.pubkey();
-    let pool_pda = get_pool_pda(
-        &program,
-        reward_duration,
-        &staking_mint,
-        reward_a_mint,
-        // reward_b_mint,
-        &base_pubkey,
-    )?;
-
-    println!("pool address {}", pool_pda.pubkey);
-
-    let VaultPDAs {
-        staking_vault,
-        reward_a_vault,
-        // reward_b_vault,
-    } = get_vault_pdas(&program.id(), &pool_pda.pubkey);
-    let (staking_vault_pubkey, _) = staking_vault;
-    let (reward_a_vault_pubkey, _) = reward_a_vault;
-    // let (reward_b_vault_pubkey, _) = reward_b_vault;
+    let pool = get_pool(program, *pool_pda)?;
+    let from_a = get_or_create_ata(&program, &funder.pubkey(), &pool.reward_a_mint)?;
+    let epoch_number = pool.epoch_count + 1;
 
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
@@ -152,42 +1526,43 @@ fn initialize_pool<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::InitializePool {
-            pool: pool_pda.pubkey,
-            staking_mint: *staking_mint,
-            staking_vault: staking_vault_pubkey,
-            reward_a_mint: *reward_a_mint,
-            reward_a_vault: reward_a_vault_pubkey,
-            // reward_b_mint: *reward_b_mint,
-            // reward_b_vault: reward_b_vault_pubkey,
-            authority: authority.pubkey(),
-            base: base_pubkey,
-            system_program: solana_program::system_program::ID,
+        accounts: farming::accounts::StartEpoch {
+            pool: *pool_pda,
+            epoch: get_epoch_pda(pool_pda, epoch_number, &program.id()),
+            reward_a_vault: pool.reward_a_vault,
+            funder: funder.pubkey(),
+            from_a,
             token_program: spl_token::ID,
-            rent: solana_program::sysvar::rent::ID,
+            system_program: solana_program::system_program::ID,
         }
         .to_account_metas(None),
-        data: farming::instruction::InitializePool { reward_duration }.data(),
+        data: farming::instruction::StartEpoch {
+            epoch_number,
+            start_time,
+            end_time,
+            budget,
+        }
+        .data(),
     });
-
     let builder = program.request();
     let builder = instructions
         .into_iter()
         .fold(builder, |bld, ix| bld.instruction(ix));
-    let builder = builder.signer(authority).signer(&base_keypair);
+    let builder = builder.signer(funder);
+
     let signature = builder.send()?;
+    println!("Epoch {}", epoch_number);
     println!("Signature {:?}", signature);
     Ok(())
 }
 
-pub fn create_user<C: Deref<Target = impl Signer> + Clone>(
+pub fn finalize_epoch<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
-    owner: &Keypair,
-    pool: &Pubkey,
+    caller: &Keypair,
+    pool_pda: &Pubkey,
 ) -> Result<()> {
-    let UserPDA { user } = get_user_pda(pool, &owner.pubkey(), &program.id());
-    let (user_pubkey, _) = user;
+    let pool = get_pool(program, *pool_pda)?;
 
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
@@ -197,32 +1572,84 @@ pub fn create_user<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::CreateUser {
-            pool: *pool,
-            user: user_pubkey,
-            owner: owner.pubkey(),
-            system_program: solana_program::system_program::ID,
+        accounts: farming::accounts::FinalizeEpoch {
+            pool: *pool_pda,
+            epoch: get_epoch_pda(pool_pda, pool.epoch_count, &program.id()),
+            caller: caller.pubkey(),
         }
         .to_account_metas(None),
-        data: farming::instruction::CreateUser {}.data(),
+        data: farming::instruction::FinalizeEpoch {}.data(),
     });
     let builder = program.request();
     let builder = instructions
         .into_iter()
         .fold(builder, |bld, ix| bld.instruction(ix));
-    let builder = builder.signer(owner);
+    let builder = builder.signer(caller);
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
     Ok(())
 }
 
-pub fn pause<C: Deref<Target = impl Signer> + Clone>(
+pub fn sweep_epoch<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    priority_fee: Option<u64>,
+    caller: &Keypair,
+    pool_pda: &Pubkey,
+    epoch: u64,
+) -> Result<()> {
+    let mut instructions = vec![];
+    if let Some(priority_fee) = priority_fee {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            priority_fee,
+        ));
+    }
+    instructions.push(Instruction {
+        program_id: program.id(),
+        accounts: farming::accounts::SweepEpoch {
+            pool: *pool_pda,
+            epoch: get_epoch_pda(pool_pda, epoch, &program.id()),
+            caller: caller.pubkey(),
+        }
+        .to_account_metas(None),
+        data: farming::instruction::SweepEpoch {}.data(),
+    });
+    let builder = program.request();
+    let builder = instructions
+        .into_iter()
+        .fold(builder, |bld, ix| bld.instruction(ix));
+    let builder = builder.signer(caller);
+
+    let signature = builder.send()?;
+    println!("Signature {:?}", signature);
+    Ok(())
+}
+
+pub fn append_emission_segment<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
     authority: &Keypair,
     pool: &Pubkey,
+    start_time: u64,
+    rate: u128,
+    halving_interval: u64,
+    action_id: Option<u64>,
 ) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::AppendEmissionSegment {
+            start_time,
+            rate,
+            halving_interval,
+        },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
         instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
@@ -231,12 +1658,18 @@ pub fn pause<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::Pause {
+        accounts: farming::accounts::UpdateEmission {
             pool: *pool,
             authority: authority.pubkey(),
+            queued_action,
         }
         .to_account_metas(None),
-        data: farming::instruction::Pause {}.data(),
+        data: farming::instruction::AppendEmissionSegment {
+            start_time,
+            rate,
+            halving_interval,
+        }
+        .data(),
     });
     let builder = program.request();
     let builder = instructions
@@ -249,12 +1682,25 @@ pub fn pause<C: Deref<Target = impl Signer> + Clone>(
     Ok(())
 }
 
-pub fn unpause<C: Deref<Target = impl Signer> + Clone>(
+pub fn set_reward_duration<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
     authority: &Keypair,
     pool: &Pubkey,
+    duration: u64,
+    action_id: Option<u64>,
 ) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool,
+        action_id,
+        AdminAction::SetRewardDuration { duration },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
         instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
@@ -263,12 +1709,13 @@ pub fn unpause<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::Unpause {
+        accounts: farming::accounts::SetRewardDuration {
             pool: *pool,
             authority: authority.pubkey(),
+            queued_action,
         }
         .to_account_metas(None),
-        data: farming::instruction::Unpause {}.data(),
+        data: farming::instruction::SetRewardDuration { duration }.data(),
     });
     let builder = program.request();
     let builder = instructions
@@ -281,18 +1728,18 @@ pub fn unpause<C: Deref<Target = impl Signer> + Clone>(
     Ok(())
 }
 
-pub fn stake<C: Deref<Target = impl Signer> + Clone>(
+pub fn claim<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
     owner: &Keypair,
     pool_pda: &Pubkey,
-    amount: u64,
 ) -> Result<()> {
     let pool = get_pool(program, *pool_pda)?;
     let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
     let (user_pubkey, _) = user;
 
-    let stake_from_account = get_or_create_ata(&program, &owner.pubkey(), &pool.staking_mint)?;
+    let reward_a_account = get_or_create_ata(&program, &owner.pubkey(), &pool.reward_a_mint)?;
+    // let reward_b_account = get_or_create_ata(&program, &owner.pubkey(), &pool.reward_b_mint)?;
 
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
@@ -302,16 +1749,19 @@ pub fn stake<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::Deposit {
+        accounts: farming::accounts::ClaimReward {
             pool: *pool_pda,
             staking_vault: pool.staking_vault,
-            stake_from_account,
+            reward_a_vault: pool.reward_a_vault,
+            // reward_b_vault: pool.reward_b_vault,
             user: user_pubkey,
             owner: owner.pubkey(),
+            reward_a_account,
+            // reward_b_account,
             token_program: spl_token::ID,
         }
         .to_account_metas(None),
-        data: farming::instruction::Deposit { amount }.data(),
+        data: farming::instruction::Claim {}.data(),
     });
     let builder = program.request();
     let builder = instructions
@@ -321,21 +1771,21 @@ pub fn stake<C: Deref<Target = impl Signer> + Clone>(
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
-
     Ok(())
 }
 
-pub fn unstake<C: Deref<Target = impl Signer> + Clone>(
+pub fn claim_epoch<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
     owner: &Keypair,
     pool_pda: &Pubkey,
-    spt_amount: u64,
+    epoch: u64,
 ) -> Result<()> {
     let pool = get_pool(program, *pool_pda)?;
     let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
     let (user_pubkey, _) = user;
-    let stake_from_account = get_or_create_ata(&program, &owner.pubkey(), &pool.staking_mint)?;
+
+    let reward_a_account = get_or_create_ata(&program, &owner.pubkey(), &pool.reward_a_mint)?;
 
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
@@ -345,16 +1795,17 @@ pub fn unstake<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::Deposit {
+        accounts: farming::accounts::ClaimEpoch {
             pool: *pool_pda,
-            staking_vault: pool.staking_vault,
-            stake_from_account,
+            epoch: get_epoch_pda(pool_pda, epoch, &program.id()),
+            reward_a_vault: pool.reward_a_vault,
             user: user_pubkey,
             owner: owner.pubkey(),
+            reward_a_account,
             token_program: spl_token::ID,
         }
         .to_account_metas(None),
-        data: farming::instruction::Withdraw { spt_amount }.data(),
+        data: farming::instruction::ClaimEpoch {}.data(),
     });
     let builder = program.request();
     let builder = instructions
@@ -364,17 +1815,31 @@ pub fn unstake<C: Deref<Target = impl Signer> + Clone>(
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
-
     Ok(())
 }
 
-pub fn authorize_funder<C: Deref<Target = impl Signer> + Clone>(
+pub fn claim_many<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
-    authority: &Keypair,
-    pool: &Pubkey,
-    funder_to_add: &Pubkey,
+    owner: &Keypair,
+    pool_pdas: &[Pubkey],
 ) -> Result<()> {
+    let mut accounts = farming::accounts::ClaimMany {
+        owner: owner.pubkey(),
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    for pool_pda in pool_pdas.iter() {
+        let pool = get_pool(program, *pool_pda)?;
+        let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
+        let (user_pubkey, _) = user;
+        let reward_a_account = get_or_create_ata(&program, &owner.pubkey(), &pool.reward_a_mint)?;
+        accounts.push(AccountMeta::new(*pool_pda, false));
+        accounts.push(AccountMeta::new(user_pubkey, false));
+        accounts.push(AccountMeta::new(pool.reward_a_vault, false));
+        accounts.push(AccountMeta::new(reward_a_account, false));
+    }
+
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
         instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
@@ -383,34 +1848,42 @@ pub fn authorize_funder<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::FunderChange {
-            pool: *pool,
-            authority: authority.pubkey(),
-        }
-        .to_account_metas(None),
-        data: farming::instruction::AuthorizeFunder {
-            funder_to_add: *funder_to_add,
-        }
-        .data(),
+        accounts,
+        data: farming::instruction::ClaimMany {}.data(),
     });
     let builder = program.request();
     let builder = instructions
         .into_iter()
         .fold(builder, |bld, ix| bld.instruction(ix));
-    let builder = builder.signer(authority);
+    let builder = builder.signer(owner);
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
     Ok(())
 }
 
-pub fn deauthorize_funder<C: Deref<Target = impl Signer> + Clone>(
+pub fn withdraw_unallocated_rewards<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
     authority: &Keypair,
-    pool: &Pubkey,
-    funder_to_remove: &Pubkey,
+    pool_pda: &Pubkey,
+    action_id: Option<u64>,
 ) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool_pda,
+        action_id,
+        AdminAction::WithdrawUnallocatedRewards,
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let pool = get_pool(program, *pool_pda)?;
+    let withdraw_to_account =
+        get_or_create_ata(&program, &authority.pubkey(), &pool.reward_a_mint)?;
+
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
         instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
@@ -419,15 +1892,16 @@ pub fn deauthorize_funder<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::FunderChange {
-            pool: *pool,
+        accounts: farming::accounts::WithdrawUnallocatedRewards {
+            pool: *pool_pda,
+            reward_a_vault: pool.reward_a_vault,
+            withdraw_to_account,
             authority: authority.pubkey(),
+            queued_action,
+            token_program: spl_token::ID,
         }
         .to_account_metas(None),
-        data: farming::instruction::DeauthorizeFunder {
-            funder_to_remove: *funder_to_remove,
-        }
-        .data(),
+        data: farming::instruction::WithdrawUnallocatedRewards {}.data(),
     });
     let builder = program.request();
     let builder = instructions
@@ -440,17 +1914,14 @@ pub fn deauthorize_funder<C: Deref<Target = impl Signer> + Clone>(
     Ok(())
 }
 
-pub fn fund<C: Deref<Target = impl Signer> + Clone>(
+pub fn close_user<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
-    funder: &Keypair,
+    owner: &Keypair,
     pool_pda: &Pubkey,
-    amount_a: u64,
-    // amount_b: u64,
 ) -> Result<()> {
-    let pool = get_pool(program, *pool_pda)?;
-    let from_a = get_or_create_ata(&program, &funder.pubkey(), &pool.reward_a_mint)?;
-    // let from_b = get_or_create_ata(&program, &funder.pubkey(), &pool.reward_b_mint)?;
+    let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
+    let (user_pubkey, _) = user;
 
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
@@ -460,43 +1931,36 @@ pub fn fund<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::Fund {
+        accounts: farming::accounts::CloseUser {
             pool: *pool_pda,
-            staking_vault: pool.staking_vault,
-            reward_a_vault: pool.reward_a_vault,
-            // reward_b_vault: pool.reward_b_vault,
-            funder: funder.pubkey(),
-            from_a,
-            // from_b,
-            token_program: spl_token::ID,
+            user: user_pubkey,
+            owner: owner.pubkey(),
         }
         .to_account_metas(None),
-        data: farming::instruction::Fund { amount_a }.data(),
+        data: farming::instruction::CloseUser {}.data(),
     });
     let builder = program.request();
     let builder = instructions
         .into_iter()
         .fold(builder, |bld, ix| bld.instruction(ix));
-    let builder = builder.signer(funder);
+    let builder = builder.signer(owner);
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
     Ok(())
 }
 
-pub fn claim<C: Deref<Target = impl Signer> + Clone>(
+pub fn set_user_frozen<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
-    owner: &Keypair,
-    pool_pda: &Pubkey,
+    authority: &Keypair,
+    pool: &Pubkey,
+    owner: &Pubkey,
+    frozen: bool,
 ) -> Result<()> {
-    let pool = get_pool(program, *pool_pda)?;
-    let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
+    let UserPDA { user } = get_user_pda(pool, owner, &program.id());
     let (user_pubkey, _) = user;
 
-    let reward_a_account = get_or_create_ata(&program, &owner.pubkey(), &pool.reward_a_mint)?;
-    // let reward_b_account = get_or_create_ata(&program, &owner.pubkey(), &pool.reward_b_mint)?;
-
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
         instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
@@ -505,39 +1969,53 @@ pub fn claim<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::ClaimReward {
-            pool: *pool_pda,
-            staking_vault: pool.staking_vault,
-            reward_a_vault: pool.reward_a_vault,
-            // reward_b_vault: pool.reward_b_vault,
+        accounts: farming::accounts::FreezeUser {
+            pool: *pool,
             user: user_pubkey,
-            owner: owner.pubkey(),
-            reward_a_account,
-            // reward_b_account,
-            token_program: spl_token::ID,
+            authority: authority.pubkey(),
         }
         .to_account_metas(None),
-        data: farming::instruction::Claim {}.data(),
+        data: if frozen {
+            farming::instruction::FreezeUser {}.data()
+        } else {
+            farming::instruction::UnfreezeUser {}.data()
+        },
     });
     let builder = program.request();
     let builder = instructions
         .into_iter()
         .fold(builder, |bld, ix| bld.instruction(ix));
-    let builder = builder.signer(owner);
+    let builder = builder.signer(authority);
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
     Ok(())
 }
 
-pub fn close_user<C: Deref<Target = impl Signer> + Clone>(
+pub fn force_close_user<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     priority_fee: Option<u64>,
-    owner: &Keypair,
+    authority: &Keypair,
     pool_pda: &Pubkey,
+    owner: &Pubkey,
+    action_id: Option<u64>,
 ) -> Result<()> {
-    let UserPDA { user } = get_user_pda(pool_pda, &owner.pubkey(), &program.id());
+    let UserPDA { user } = get_user_pda(pool_pda, owner, &program.id());
     let (user_pubkey, _) = user;
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool_pda,
+        action_id,
+        AdminAction::ForceCloseUser { user: user_pubkey },
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
+    let pool = get_pool(program, *pool_pda)?;
+    let owner_staking_account = get_or_create_ata(&program, owner, &pool.staking_mint)?;
+    let owner_reward_a_account = get_or_create_ata(&program, owner, &pool.reward_a_mint)?;
 
     let mut instructions = vec![];
     if let Some(priority_fee) = priority_fee {
@@ -547,19 +2025,26 @@ pub fn close_user<C: Deref<Target = impl Signer> + Clone>(
     }
     instructions.push(Instruction {
         program_id: program.id(),
-        accounts: farming::accounts::CloseUser {
+        accounts: farming::accounts::ForceCloseUser {
             pool: *pool_pda,
+            staking_vault: pool.staking_vault,
+            reward_a_vault: pool.reward_a_vault,
             user: user_pubkey,
-            owner: owner.pubkey(),
+            owner: *owner,
+            owner_staking_account,
+            owner_reward_a_account,
+            authority: authority.pubkey(),
+            token_program: spl_token::ID,
+            queued_action,
         }
         .to_account_metas(None),
-        data: farming::instruction::CloseUser {}.data(),
+        data: farming::instruction::ForceCloseUser {}.data(),
     });
     let builder = program.request();
     let builder = instructions
         .into_iter()
         .fold(builder, |bld, ix| bld.instruction(ix));
-    let builder = builder.signer(owner);
+    let builder = builder.signer(authority);
 
     let signature = builder.send()?;
     println!("Signature {:?}", signature);
@@ -571,10 +2056,23 @@ pub fn close_pool<C: Deref<Target = impl Signer> + Clone>(
     priority_fee: Option<u64>,
     authority: &Keypair,
     pool_pda: &Pubkey,
+    action_id: Option<u64>,
 ) -> Result<()> {
+    let queued_action = match timelock_step(
+        program,
+        priority_fee,
+        authority,
+        pool_pda,
+        action_id,
+        AdminAction::ClosePool,
+    )? {
+        TimelockStep::Queued => return Ok(()),
+        TimelockStep::Execute(queued_action) => queued_action,
+    };
     let pool = get_pool(program, *pool_pda)?;
     let staking_refundee = get_or_create_ata(&program, &authority.pubkey(), &pool.staking_mint)?;
     let reward_a_refundee = get_or_create_ata(&program, &authority.pubkey(), &pool.reward_a_mint)?;
+    let pool_metadata = get_pool_metadata_pda(pool_pda, &program.id());
     // let reward_b_refundee: Pubkey = get_or_create_ata(&program, &authority.pubkey(), &pool.reward_b_mint)?;
 
     let mut instructions = vec![];
@@ -591,11 +2089,15 @@ pub fn close_pool<C: Deref<Target = impl Signer> + Clone>(
             reward_a_refundee,
             // reward_b_refundee,
             pool: *pool_pda,
+            pool_index: get_pool_index_pda(&pool.staking_mint, &program.id()),
+            pool_metadata,
             authority: authority.pubkey(),
             staking_vault: pool.staking_vault,
             reward_a_vault: pool.reward_a_vault,
             // reward_b_vault: pool.reward_b_vault,
             token_program: spl_token::ID,
+            system_program: solana_program::system_program::ID,
+            queued_action,
         }
         .to_account_metas(None),
         data: farming::instruction::ClosePool {}.data(),
@@ -620,6 +2122,16 @@ pub fn show_info<C: Deref<Target = impl Signer> + Clone>(
     println!("pool_pubkey {:#?}", pool_pda);
     println!("user_stake_count {:#?}", pool.user_stake_count);
     println!("staking_vault {:#?}", pool.staking_vault);
+    let pool_metadata = program
+        .account::<farming::PoolMetadata>(get_pool_metadata_pda(pool_pda, &program.id()))
+        .ok();
+    if let Some(pool_metadata) = pool_metadata {
+        println!("name {}", pool_metadata.name);
+        println!("symbol {}", pool_metadata.symbol);
+        println!("uri {}", pool_metadata.uri);
+        println!("tags {}", pool_metadata.tags.join(", "));
+        println!("created_at {}", pool_metadata.created_at);
+    }
 
     Ok(())
 }
@@ -649,6 +2161,16 @@ pub fn stake_info<C: Deref<Target = impl Signer> + Clone>(
     //     "reward_b_per_token_pending {:#?}",
     //     user.reward_b_per_token_pending
     // );
+    println!("immature_balance {:#?}", user.immature_balance);
+    println!("matures_at {:#?}", user.matures_at);
+    println!("frozen {:#?}", user.frozen);
+    println!("points {:#?}", user.points);
+    for record in user.epoch_stakes.iter().filter(|r| r.epoch != 0) {
+        println!(
+            "epoch {} stake_seconds {} claimed {}",
+            record.epoch, record.stake_seconds, record.claimed
+        );
+    }
     Ok(())
 }
 
@@ -666,31 +2188,80 @@ fn check_funder_all_pool<C: Deref<Target = impl Signer> + Clone>(
     Ok(())
 }
 
-fn migrate_farming_rate<C: Deref<Target = impl Signer> + Clone>(
+/// Deserializes a program account, zero padding older, shorter layouts
+fn padded_account<T: AccountDeserialize>(data: &[u8], len: usize) -> Option<T> {
+    let mut padded = data.to_vec();
+    if padded.len() < 8 + len {
+        padded.resize(8 + len, 0);
+    }
+    T::try_deserialize(&mut padded.as_slice()).ok()
+}
+
+fn migrate<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
+    priority_fee: Option<u64>,
+    payer: &Keypair,
 ) -> Result<()> {
-    let pools: Vec<(Pubkey, Pool)> = program.accounts::<Pool>(vec![]).unwrap();
+    let accounts = program.rpc().get_program_accounts(&program.id())?;
 
-    println!("len pool {}", pools.len());
+    // pools go first, migrating a user loads its pool at the current layout
+    let mut outdated_pools = vec![];
+    let mut outdated_users = vec![];
+    for (pubkey, account) in accounts.iter() {
+        if account.data.starts_with(&Pool::discriminator()) {
+            let pool = padded_account::<Pool>(&account.data, Pool::LEN);
+            if let Some(pool) = pool.filter(|pool| pool.version < farming::POOL_VERSION) {
+                outdated_pools.push((*pubkey, Instruction {
+                    program_id: program.id(),
+                    accounts: farming::accounts::MigrateAccount {
+                        account: *pubkey,
+                        staking_mint: pool.staking_mint,
+                        reward_a_mint: pool.reward_a_mint,
+                        payer: payer.pubkey(),
+                        system_program: solana_program::system_program::ID,
+                    }
+                    .to_account_metas(None),
+                    data: farming::instruction::MigratePool {}.data(),
+                }));
+            }
+        } else if account.data.starts_with(&User::discriminator()) {
+            let user = padded_account::<User>(&account.data, User::LEN);
+            if let Some(user) = user.filter(|user| user.version < farming::USER_VERSION) {
+                outdated_users.push((*pubkey, Instruction {
+                    program_id: program.id(),
+                    accounts: farming::accounts::MigrateUser {
+                        pool: user.pool,
+                        account: *pubkey,
+                        payer: payer.pubkey(),
+                        system_program: solana_program::system_program::ID,
+                    }
+                    .to_account_metas(None),
+                    data: farming::instruction::MigrateUser {}.data(),
+                }));
+            }
+        }
+    }
 
-    for pool in pools.iter() {
-        let pool_state = pool.1.clone();
-        let mut should_migrate = false;
-        if pool_state.reward_a_rate_u128 == 0 && pool_state._reward_a_rate != 0 {
-            should_migrate = true;
-        }
-        // if pool_state.reward_b_rate_u128 == 0 && pool_state._reward_b_rate != 0 {
-        //     should_migrate = true;
-        // }
-
-        if should_migrate {
-            let builder = program
-                .request()
-                .accounts(farming::accounts::MigrateFarmingRate { pool: pool.0 })
-                .args(farming::instruction::MigrateFarmingRate {});
-            let signature = builder.send()?;
-            println!("Migrate pool {} signature {:?}", pool.0, signature);
+    println!(
+        "len outdated accounts {}",
+        outdated_pools.len() + outdated_users.len()
+    );
+
+    for (pubkey, migrate_ix) in outdated_pools.into_iter().chain(outdated_users) {
+        let mut instructions = vec![];
+        if let Some(priority_fee) = priority_fee {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                priority_fee,
+            ));
         }
+        instructions.push(migrate_ix);
+        let builder = program.request();
+        let builder = instructions
+            .into_iter()
+            .fold(builder, |bld, ix| bld.instruction(ix));
+        let builder = builder.signer(payer);
+        let signature = builder.send()?;
+        println!("Migrate account {} signature {:?}", pubkey, signature);
     }
     Ok(())
 }
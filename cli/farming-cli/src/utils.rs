@@ -23,7 +23,7 @@ pub fn get_or_create_ata<C: Deref<Target = impl Signer> + Clone>(
     wallet_address: &Pubkey,
     token_mint: &Pubkey,
 ) -> Result<Pubkey> {
-    let ata_account = get_associated_token_address(&program.payer(), token_mint);
+    let ata_account = get_associated_token_address(wallet_address, token_mint);
     let ata_account_info = program.rpc().get_account(&ata_account);
     if ata_account_info.is_err() {
         println!("Create ATA {:?} for Mint {:?}", ata_account, token_mint);
@@ -68,6 +68,38 @@ pub fn get_pool_pda<C: Deref<Target = impl Signer> + Clone>(
     })
 }
 
+pub fn get_global_config_pda(program_id: &Pubkey) -> Pubkey {
+    let (global_config_pubkey, _) =
+        Pubkey::find_program_address(&[b"global_config"], &program_id);
+    global_config_pubkey
+}
+
+pub fn get_pool_index_pda(staking_mint: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    let seeds = [b"pool_index".as_ref(), staking_mint.as_ref()];
+    let (pool_index_pubkey, _) = Pubkey::find_program_address(&seeds, &program_id);
+    pool_index_pubkey
+}
+
+pub fn get_pool_metadata_pda(pool: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    let seeds = [b"metadata".as_ref(), pool.as_ref()];
+    let (pool_metadata_pubkey, _) = Pubkey::find_program_address(&seeds, &program_id);
+    pool_metadata_pubkey
+}
+
+pub fn get_queued_action_pda(pool: &Pubkey, action_id: u64, program_id: &Pubkey) -> Pubkey {
+    let action_id = action_id.to_le_bytes();
+    let seeds = [b"queued_action".as_ref(), pool.as_ref(), action_id.as_ref()];
+    let (queued_action_pubkey, _) = Pubkey::find_program_address(&seeds, &program_id);
+    queued_action_pubkey
+}
+
+pub fn get_epoch_pda(pool: &Pubkey, epoch: u64, program_id: &Pubkey) -> Pubkey {
+    let epoch = epoch.to_le_bytes();
+    let seeds = [b"epoch".as_ref(), pool.as_ref(), epoch.as_ref()];
+    let (epoch_pubkey, _) = Pubkey::find_program_address(&seeds, &program_id);
+    epoch_pubkey
+}
+
 pub fn get_pool<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     pool_pubkey: Pubkey,
@@ -4,6 +4,8 @@
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::integer_arithmetic)]
 #![warn(missing_docs)]
+// anchor errors are large by design, every instruction returns one
+#![allow(clippy::result_large_err)]
 
 use std::convert::Into;
 use std::convert::TryInto;
@@ -12,6 +14,7 @@ use std::fmt::Debug;
 use crate::pool::*;
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::{clock, sysvar};
+use anchor_spl::associated_token::get_associated_token_address;
 use anchor_spl::token::spl_token;
 use anchor_spl::token::{self, Mint, Token, TokenAccount};
 use std::convert::TryFrom;
@@ -23,6 +26,54 @@ declare_id!("kJsQ8P7cKZneV3tTQPGP8xKz5mpMxrgDatWNQRiYmvG");
 
 const PRECISION: u128 = 1_000_000_000;
 const TOTAL_ANNUAL_REWARD:u64=2_100_000;
+/// Highest tier multiplier applied by `update_rewards`
+const MAX_REWARD_MULTIPLIER: u64 = 4;
+/// Maximum number of segments in a pool emission schedule
+pub const MAX_EMISSION_SEGMENTS: usize = 8;
+/// Seconds after `reward_duration_end` before the authority may force close user accounts
+pub const FORCE_CLOSE_GRACE_PERIOD: u64 = 30 * 24 * 60 * 60;
+/// Seconds after the end of an epoch during which its budget can be claimed, before `sweep_epoch` releases the rest
+pub const EPOCH_CLAIM_PERIOD: u64 = 90 * 24 * 60 * 60;
+/// Pause flag blocking `deposit` and `create_user`
+pub const PAUSE_DEPOSIT: u8 = 1 << 0;
+/// Pause flag blocking `withdraw`
+pub const PAUSE_WITHDRAW: u8 = 1 << 1;
+/// Pause flag blocking `claim`
+pub const PAUSE_CLAIM: u8 = 1 << 2;
+/// Pause flag blocking `fund`
+pub const PAUSE_FUND: u8 = 1 << 3;
+/// All pause flags
+pub const PAUSE_ALL: u8 = PAUSE_DEPOSIT | PAUSE_WITHDRAW | PAUSE_CLAIM | PAUSE_FUND;
+/// Maximum number of staking mints in the global allowlist
+pub const MAX_ALLOWED_STAKING_MINTS: usize = 16;
+/// Maximum number of pools listed in a pool index
+pub const MAX_INDEXED_POOLS: usize = 32;
+/// Basis points denominator for fee settings
+pub const MAX_FEE_BPS: u16 = 10_000;
+/// Number of `claim_many` remaining accounts per pool: pool, user, reward A vault, reward A destination
+pub const CLAIM_MANY_GROUP_LEN: usize = 4;
+/// Maximum number of points in a pool utilization curve
+pub const MAX_CURVE_POINTS: usize = 4;
+/// Number of recent epochs whose stake-seconds are kept on pools and users
+pub const EPOCH_HISTORY: usize = 4;
+/// Number of deposits in the early exit window tracked separately on users
+pub const EARLY_EXIT_LOTS: usize = 4;
+/// Longest timelock a pool can set on its admin actions
+pub const MAX_TIMELOCK_SECONDS: u64 = 30 * 24 * 60 * 60;
+/// Current layout version of pool accounts
+pub const POOL_VERSION: u8 = 12;
+/// Current layout version of user accounts
+pub const USER_VERSION: u8 = 7;
+/// Maximum length in bytes of a pool name
+pub const MAX_POOL_NAME_LEN: usize = 32;
+/// Maximum length in bytes of a pool symbol
+pub const MAX_POOL_SYMBOL_LEN: usize = 10;
+/// Maximum length in bytes of a pool metadata URI
+pub const MAX_POOL_URI_LEN: usize = 200;
+/// Maximum number of pool tags
+pub const MAX_POOL_TAGS: usize = 5;
+/// Maximum length in bytes of a pool tag
+pub const MAX_POOL_TAG_LEN: usize = 16;
 
 /// Updates the pool with the total reward per token that is due stakers
 /// Using the calculator specific to that pool version which uses the reward
@@ -44,64 +95,423 @@ pub fn update_rewards(
     // if pool.reward_a_vault != pool.reward_b_vault {
     //     pool.reward_b_per_token_stored = reward_b;
     // }   
+    let now: u64 = clock::Clock::get()?
+        .unix_timestamp
+        .try_into()
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    pool.checkpoint(now).ok_or(ErrorCode::MathOverflow)?;
+    pool.checkpoint_points(now).ok_or(ErrorCode::MathOverflow)?;
+    pool.checkpoint_epoch(now).ok_or(ErrorCode::MathOverflow)?;
 
     if let Some(u) = user {
-
         let current_time:u64=clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-        let time_period:u64 = current_time
-            .checked_sub(pool.last_update_time)
-            .unwrap().into();
+        u.points = user_points(pool, u, current_time).ok_or(ErrorCode::MathOverflow)?;
+        u.points_per_token_complete = pool.points_per_token_stored;
+        u.record_stake_seconds(pool.epochs, current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (_, credited) = if pool.points_only {
+            (0, 0)
+        } else {
+            user_accrued_reward(pool, u, total_staked, current_time)
+                .ok_or(ErrorCode::MathOverflow)?
+        };
+        if u.matures_at <= current_time {
+            u.immature_balance = 0;
+        }
+
+        // accrued_reward never credits more than what is left of the budget
+        u.reward_a_per_token_pending=u.reward_a_per_token_pending.checked_add(credited).ok_or(ErrorCode::MathOverflow)?;
+        pool.total_reward=pool.total_reward.checked_sub(credited).ok_or(ErrorCode::MathOverflow)?;
+        pool.total_pending=pool.total_pending.checked_add(credited).ok_or(ErrorCode::MathOverflow)?;
+        pool.last_update_time = current_time;
+    }
+    Ok(())
+}
 
-        
+/// Checks that `action` has been queued and its timelock has passed when the pool has a timelock.
+/// The queued action, when given, must match and is closed by its account constraint.
+fn check_timelock(
+    pool: &Pool,
+    queued_action: Option<&Account<QueuedAction>>,
+    action: AdminAction,
+    authority: Pubkey,
+) -> Result<()> {
+    let queued_action = match queued_action {
+        Some(queued_action) => queued_action,
+        None if pool.timelock_seconds == 0 => return Ok(()),
+        None => return Err(ErrorCode::TimelockRequired.into()),
+    };
+    if queued_action.action != action {
+        return Err(ErrorCode::QueuedActionMismatch.into());
+    }
+    let clock = clock::Clock::get()?;
+    let current_time: u64 = clock
+        .unix_timestamp
+        .try_into()
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    // a timelock raised after queueing applies to the queued action too
+    let executable_at = std::cmp::max(
+        queued_action.eta,
+        queued_action
+            .queued_at
+            .checked_add(pool.timelock_seconds)
+            .ok_or(ErrorCode::MathOverflow)?,
+    );
+    if current_time < executable_at {
+        return Err(ErrorCode::TimelockNotExpired.into());
+    }
+    emit!(EventExecuteAction {
+        pool: queued_action.pool,
+        authority,
+        queued_action: queued_action.key(),
+        action_id: queued_action.action_id,
+        action,
+        eta: executable_at,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+    Ok(())
+}
 
-        let mut time_period_days:u64= time_period.checked_div(1).unwrap().into();//seconds
-        if time_period_days<1 {
-            time_period_days=1;
-        }
-        let mut pool_balance_factor=total_staked;
-        if pool_balance_factor<1 {
-            pool_balance_factor=1;
-        }
+/// Records an outflow of `amount` against the pool outflow limit, failing when it would go over.
+fn check_outflow(pool: &mut Pool, amount: u64, current_time: u64) -> Result<()> {
+    if !pool
+        .record_outflow(amount, current_time)
+        .ok_or(ErrorCode::MathOverflow)?
+    {
+        return Err(ErrorCode::OutflowLimitExceeded.into());
+    }
+    Ok(())
+}
+
+/// Records a withdrawal of `amount` by `caller` against the pool outflow limit and returns whether it
+/// can go ahead. One that does not fit in what the window has left pauses withdrawals until the end
+/// of the window and emits an alert instead. One over the limit itself fails, it must be split.
+fn record_withdraw_outflow(
+    pool: &mut Box<Account<Pool>>,
+    caller: Pubkey,
+    amount: u64,
+    current_time: u64,
+) -> Result<bool> {
+    if pool
+        .record_outflow(amount, current_time)
+        .ok_or(ErrorCode::MathOverflow)?
+    {
+        return Ok(true);
+    }
+    if !pool
+        .outflow_limit_reached(amount, current_time)
+        .ok_or(ErrorCode::MathOverflow)?
+    {
+        return Err(ErrorCode::OutflowLimitExceeded.into());
+    }
+    pool.outflow_paused_until = pool
+        .outflow_window_start
+        .checked_add(pool.outflow_window_seconds)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let clock = clock::Clock::get()?;
+    emit!(EventOutflowLimitTripped {
+        pool: pool.key(),
+        caller,
+        amount,
+        window_amount: pool.outflow_window_amount,
+        window_base: pool.outflow_window_base,
+        limit_bps: pool.outflow_limit_bps,
+        window_start: pool.outflow_window_start,
+        paused_until: pool.outflow_paused_until,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+    Ok(false)
+}
 
-        let mut reward_unit:u64=TOTAL_ANNUAL_REWARD
-        .checked_mul(u.balance_staked.into())
-        .unwrap()
-        .checked_mul(time_period_days.into())
-        .unwrap()
-        .checked_div(pool_balance_factor.into())
-        .unwrap()
-        .checked_div(365)
-        .unwrap()
-        .into();
+/// Initializes a freshly created user staking account and counts it on the pool
+fn init_user(
+    pool: &mut Box<Account<Pool>>,
+    user: &mut Box<Account<User>>,
+    owner: Pubkey,
+    nonce: u8,
+) -> Result<()> {
+    user.pool = pool.key();
+    user.owner = owner;
+    user.reward_a_per_token_complete = 0;
+    // user.reward_b_per_token_complete = 0;
+    user.reward_a_per_token_pending = 0;
+    // user.reward_b_per_token_pending = 0;
+    user.balance_staked = 0;
+    user.immature_balance = 0;
+    user.matures_at = 0;
+    user.points = 0;
+    user.points_per_token_complete = pool.points_per_token_stored;
+    user.nonce = nonce;
+    user.version = USER_VERSION;
 
+    pool.user_stake_count = pool
+        .user_stake_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let clock = clock::Clock::get()?;
+    emit!(EventCreateUser {
+        pool: pool.key(),
+        owner,
+        user: user.key(),
+        user_stake_count: pool.user_stake_count,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+    Ok(())
+}
 
-        // u.reward_a_per_token_pending=time_period_days.into();
-        let mut reward_pending:u64=0;
-        if time_period_days<1 {//less than 1 seconds
-            reward_pending = 0;
-        }
-        else if time_period_days<4 {//1x reward
-            reward_pending=reward_unit;
-        }
-        else if time_period_days<8 {//2x reward
-            reward_pending=reward_unit.checked_mul(2).unwrap();
-        }
-        else if  time_period_days<16 {//3x reward
-            reward_pending=reward_unit.checked_mul(3).unwrap();
+/// Loads the user staking account at its `[owner, pool]` PDA. It is created on first use and a user
+/// account predating the current layout is grown and migrated, which `init_if_needed` cannot do.
+/// Changes are written back by `exit`, the account is not part of the instruction accounts struct.
+fn load_or_init_user<'info>(
+    pool: &mut Box<Account<'info, Pool>>,
+    user: &UncheckedAccount<'info>,
+    owner: Pubkey,
+    nonce: u8,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<Box<Account<'info, User>>> {
+    let info = user.to_account_info();
+    if info.owner == &anchor_lang::system_program::ID {
+        let pool_key = pool.key();
+        create_pda_account(
+            &info,
+            payer,
+            system_program,
+            8 + User::LEN,
+            &[owner.as_ref(), pool_key.as_ref(), &[nonce]],
+        )?;
+        {
+            let mut data = info.try_borrow_mut_data()?;
+            let mut writer: &mut [u8] = &mut data;
+            User::default().try_serialize(&mut writer)?;
         }
-        else { //4x reward
-            reward_pending=reward_unit.checked_mul(4).unwrap();
+        let mut account = Box::new(Account::<User>::try_from(&info)?);
+        init_user(pool, &mut account, owner, nonce)?;
+        return Ok(account);
+    }
+    grow_account(&info, payer, system_program, 8 + User::LEN)?;
+    let mut account = Box::new(Account::<User>::try_from(&info)?);
+    if account.pool != pool.key() {
+        return Err(ErrorCode::InvalidUserPool.into());
+    }
+    let old_version = migrate_user_account(pool, &mut account)?;
+    if old_version != account.version {
+        let clock = clock::Clock::get()?;
+        emit!(EventMigrate {
+            pool: pool.key(),
+            account: account.key(),
+            payer: payer.key(),
+            old_version,
+            new_version: account.version,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+    }
+    Ok(account)
+}
+
+/// Whether `account`, a user PDA that may not have been created, holds a frozen user.
+/// Users predating the frozen flag are too short to deserialize and cannot have been frozen.
+fn is_frozen_user(account: &AccountInfo) -> Result<bool> {
+    if account.owner != &crate::ID || account.data_len() < 8 + User::LEN {
+        return Ok(false);
+    }
+    let user = User::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    Ok(user.frozen)
+}
+
+/// Brings a user grown to the current layout up to USER_VERSION and returns the version it had.
+/// The pending rewards of users predating total_pending are counted in, the pool must be migrated first.
+fn migrate_user_account(pool: &mut Pool, user: &mut User) -> Result<u8> {
+    let old_version = user.version;
+    if old_version == 0 {
+        // pending rewards credited before the pool tracked them
+        pool.total_pending = pool
+            .total_pending
+            .checked_add(user.reward_a_per_token_pending)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.legacy_user_count = pool.legacy_user_count.saturating_sub(1);
+    }
+    user.migrate();
+    Ok(old_version)
+}
+
+/// Credits pending rewards and moves `amount` from `stake_from_account`, signed by `depositor`,
+/// into the staking vault on behalf of `user`. Accounts must already be validated.
+/// A deposit by anyone but the owner never restarts the owner's min stake or early exit windows.
+fn stake_tokens<'info>(
+    pool: &mut Box<Account<'info, Pool>>,
+    user: &mut Box<Account<'info, User>>,
+    stake_from_account: &Account<'info, TokenAccount>,
+    staking_vault: &Account<'info, TokenAccount>,
+    depositor: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+    }
+    if pool.is_paused(PAUSE_DEPOSIT) {
+        return Err(ErrorCode::DepositPaused.into());
+    }
+    if user.frozen {
+        return Err(ErrorCode::UserFrozen.into());
+    }
+    let balance_before = user.balance_staked;
+    update_rewards(pool, Some(user), pool.total_staked)?;
+
+    user.balance_staked = user
+        .balance_staked
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let now: u64 = clock::Clock::get()?
+        .unix_timestamp
+        .try_into()
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    let own_deposit = depositor.key() == user.owner;
+    if own_deposit {
+        user.last_deposit_time = now;
+    }
+    if pool.early_exit_penalty_bps > 0 {
+        user.add_early_exit_lot(amount, pool.early_exit_window_seconds, now, own_deposit)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    if pool.min_stake_seconds > 0 {
+        // an owner deposit restarts the maturity of the whole immature stake,
+        // someone else's deposit matures along with the immature stake it joins
+        if own_deposit || user.immature_balance == 0 {
+            user.matures_at = now
+                .checked_add(pool.min_stake_seconds)
+                .ok_or(ErrorCode::MathOverflow)?;
         }
-        
-        if pool.total_reward > reward_pending {
-            u.reward_a_per_token_pending=u.reward_a_per_token_pending.checked_add(reward_pending).unwrap();
-            pool.total_reward=pool.total_reward.checked_sub(reward_pending).unwrap();
-        }else{
-            u.reward_a_per_token_pending=pool.total_reward;
-            pool.total_reward=0;
+        user.immature_balance = user
+            .immature_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    // Transfer tokens into the stake vault.
+    let cpi_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        token::Transfer {
+            from: stake_from_account.to_account_info(),
+            to: staking_vault.to_account_info(),
+            authority: depositor.to_account_info(),
+        },
+    );
+    token::transfer(cpi_ctx, amount)?;
+    pool.total_staked = pool
+        .total_staked
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let clock = clock::Clock::get()?;
+    pool.last_update_time = clock
+        .unix_timestamp
+        .try_into()
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    emit!(EventDeposit {
+        pool: pool.key(),
+        owner: user.owner,
+        payer: depositor.key(),
+        amount,
+        balance_before,
+        balance_after: user.balance_staked,
+        reward_pending: user.reward_a_per_token_pending,
+        total_staked: pool.total_staked,
+        total_reward: pool.total_reward,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Credits the user's rewards and transfers them from the reward A vault to `reward_a_account`.
+/// Shared by `claim` and `claim_many`, accounts must already be validated.
+fn claim_rewards<'info>(
+    pool: &mut Box<Account<'info, Pool>>,
+    user: &mut Box<Account<'info, User>>,
+    reward_a_vault: &Account<'info, TokenAccount>,
+    reward_a_account: &Account<'info, TokenAccount>,
+    owner: Pubkey,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    if pool.is_paused(PAUSE_CLAIM) {
+        return Err(ErrorCode::ClaimPaused.into());
+    }
+    if user.frozen {
+        return Err(ErrorCode::UserFrozen.into());
+    }
+    let clock = clock::Clock::get()?;
+    let current_time: u64 = clock
+        .unix_timestamp
+        .try_into()
+        .map_err(|_| ErrorCode::MathOverflow)?;
+
+    if user.is_immature(current_time) {
+        return Err(ErrorCode::StakeNotMatured.into());
+    }
+
+    update_rewards(pool, Some(user), pool.total_staked)?;
+
+    pool.last_update_time = current_time;
+    let pending_before = user.reward_a_per_token_pending;
+    // the early exit penalty of such pools comes out of the pending rewards,
+    // keep back the share earned by the stake in its window until the window ends
+    let withheld = if pool.early_exit_penalty_bps > 0 && pool.staking_mint != pool.reward_a_mint {
+        user.withhold_early_exit_rewards(pool.early_exit_window_seconds, current_time)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        0
+    };
+    let claimable = pending_before.saturating_sub(withheld);
+    pool.total_pending = pool.total_pending.saturating_sub(claimable);
+
+    let mut claimed_reward_a: u64 = 0;
+    if claimable > 0 {
+        // the epoch budgets in the vault are not for pending rewards
+        let available = reward_a_vault.amount.saturating_sub(pool.epoch_reserved);
+        let reward_amount = std::cmp::min(claimable, available);
+        user.reward_a_per_token_pending = pending_before.saturating_sub(claimable);
+
+        if reward_amount > 0 {
+            let reward_duration = pool.reward_duration.to_be_bytes();
+            let seeds = &[
+                reward_duration.as_ref(),
+                pool.staking_mint.as_ref(),
+                pool.reward_a_mint.as_ref(),
+                pool.base_key.as_ref(),
+                &[pool.pool_bump],
+            ];
+            let pool_signer = &[&seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                token::Transfer {
+                    from: reward_a_vault.to_account_info(),
+                    to: reward_a_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, reward_amount)?;
+            claimed_reward_a = reward_amount;
         }
-        pool.last_update_time = current_time;
     }
+
+    emit!(EventClaim {
+        pool: pool.key(),
+        owner,
+        amount_a: claimed_reward_a,
+        pending_before,
+        pending_after: user.reward_a_per_token_pending,
+        balance_staked: user.balance_staked,
+        total_staked: pool.total_staked,
+        total_reward: pool.total_reward,
+        slot: clock.slot,
+        timestamp: clock.unix_timestamp,
+    });
     Ok(())
 }
 
@@ -121,21 +531,181 @@ pub mod farming {
     ///Charge Reward
     pub fn charge_reward(ctx:Context<ChargeReward>,charge_amount:u64)->Result<()>{
         let pool=&mut ctx.accounts.pool;
+        let total_reward_before = pool.total_reward;
         pool.total_reward=pool.total_reward.checked_add(charge_amount.into()).unwrap();
+        let clock = clock::Clock::get()?;
+        emit!(EventChargeReward {
+            pool: pool.key(),
+            signer: ctx.accounts.signer.key(),
+            amount: charge_amount,
+            total_reward_before,
+            total_reward: pool.total_reward,
+            total_staked: pool.total_staked,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
     ///Get Total Reward remaining in Pool
     pub fn remaining_reward(ctx:Context<RemainingReward>)->Result<u64>{
-        let pool=&mut ctx.accounts.pool;
+        let pool=&ctx.accounts.pool;
         Ok(pool.total_reward)
     }
 
+    /// Rewards the user could claim now: pending rewards plus what `update_rewards` would credit.
+    /// Read-only, meant to be simulated.
+    pub fn get_pending_rewards(ctx: Context<GetUserPosition>) -> Result<u64> {
+        Ok(user_position(&ctx.accounts.pool, &ctx.accounts.user)?.pending)
+    }
+
+    /// Points the user has earned so far, including those `update_rewards` would add.
+    /// Read-only, meant to be simulated.
+    pub fn get_points(ctx: Context<GetUserPosition>) -> Result<u128> {
+        Ok(user_position(&ctx.accounts.pool, &ctx.accounts.user)?.points)
+    }
+
+    /// Staked balance, current multiplier tier and claimable rewards of a user.
+    /// Read-only, meant to be simulated.
+    pub fn get_user_position(ctx: Context<GetUserPosition>) -> Result<UserPosition> {
+        user_position(&ctx.accounts.pool, &ctx.accounts.user)
+    }
+
+    /// TVL, remaining rewards, current emission rate and time left of a pool.
+    /// Read-only, meant to be simulated.
+    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+        let pool = &ctx.accounts.pool;
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let total_staked = std::cmp::max(pool.total_staked, 1);
+        let reward_rate = base_reward(
+            pool,
+            total_staked,
+            total_staked,
+            current_time,
+            current_time.checked_add(1).ok_or(ErrorCode::MathOverflow)?,
+        )
+        .ok_or(ErrorCode::MathOverflow)?;
+        Ok(PoolStats {
+            total_staked: pool.total_staked,
+            remaining_reward: pool.total_reward,
+            total_pending: pool.total_pending,
+            reward_rate,
+            reward_start_time: pool.reward_start_time,
+            reward_duration_end: pool.reward_duration_end,
+            time_left: pool.reward_duration_end.saturating_sub(current_time),
+            user_stake_count: pool.user_stake_count,
+        })
+    }
+
+    /// Initializes the program-wide config. Only the program upgrade authority can, and becomes the program admin.
+    pub fn initialize_global_config(
+        ctx: Context<InitializeGlobalConfig>,
+        default_fee_bps: u16,
+        fee_recipient: Pubkey,
+    ) -> Result<()> {
+        if default_fee_bps > MAX_FEE_BPS {
+            return Err(ErrorCode::InvalidFeeBps.into());
+        }
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.admin = ctx.accounts.admin.key();
+        global_config.default_fee_bps = default_fee_bps;
+        global_config.fee_recipient = fee_recipient;
+        global_config.bump = *ctx.bumps.get("global_config").unwrap();
+        let clock = clock::Clock::get()?;
+        emit!(EventUpdateGlobalConfig {
+            admin: global_config.admin,
+            new_admin: global_config.admin,
+            default_fee_bps,
+            fee_recipient,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Updates the program admin and the default fee settings
+    pub fn update_global_config(
+        ctx: Context<GlobalConfigChange>,
+        admin: Pubkey,
+        default_fee_bps: u16,
+        fee_recipient: Pubkey,
+    ) -> Result<()> {
+        if default_fee_bps > MAX_FEE_BPS {
+            return Err(ErrorCode::InvalidFeeBps.into());
+        }
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.admin = admin;
+        global_config.default_fee_bps = default_fee_bps;
+        global_config.fee_recipient = fee_recipient;
+        let clock = clock::Clock::get()?;
+        emit!(EventUpdateGlobalConfig {
+            admin: ctx.accounts.admin.key(),
+            new_admin: admin,
+            default_fee_bps,
+            fee_recipient,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Adds a staking mint to the allowlist. An empty allowlist allows any staking mint.
+    pub fn allow_staking_mint(ctx: Context<GlobalConfigChange>, mint: Pubkey) -> Result<()> {
+        let allowed = &mut ctx.accounts.global_config.allowed_staking_mints;
+        if allowed.contains(&mint) {
+            return Err(ErrorCode::StakingMintAlreadyAllowed.into());
+        }
+        let default_pubkey = Pubkey::default();
+        if let Some(idx) = allowed.iter().position(|x| *x == default_pubkey) {
+            allowed[idx] = mint;
+            let clock = clock::Clock::get()?;
+            emit!(EventAllowStakingMint {
+                admin: ctx.accounts.admin.key(),
+                mint,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+            });
+        } else {
+            return Err(ErrorCode::MaxAllowedStakingMints.into());
+        }
+        Ok(())
+    }
+
+    /// Removes a staking mint from the allowlist
+    pub fn disallow_staking_mint(ctx: Context<GlobalConfigChange>, mint: Pubkey) -> Result<()> {
+        let allowed = &mut ctx.accounts.global_config.allowed_staking_mints;
+        if let Some(idx) = allowed.iter().position(|x| *x == mint) {
+            allowed[idx] = Pubkey::default();
+            let clock = clock::Clock::get()?;
+            emit!(EventDisallowStakingMint {
+                admin: ctx.accounts.admin.key(),
+                mint,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+            });
+        } else {
+            return Err(ErrorCode::StakingMintNotAllowed.into());
+        }
+        Ok(())
+    }
+
     /// Initializes a new pool. Able to create pool with single reward by passing the same Mint account for reward_a_mint and reward_a_mint
     pub fn initialize_pool(ctx: Context<InitializePool>, reward_duration: u64) -> Result<()> {
         if reward_duration < MIN_DURATION {
             return Err(ErrorCode::DurationTooShort.into());
         }
+        let staking_mint = ctx.accounts.staking_mint.key();
+        if !ctx.accounts.global_config.is_staking_mint_allowed(&staking_mint) {
+            return Err(ErrorCode::StakingMintNotAllowed.into());
+        }
+        let pool_index = &mut ctx.accounts.pool_index;
+        pool_index.staking_mint = staking_mint;
+        pool_index.bump = *ctx.bumps.get("pool_index").unwrap();
+        pool_index.add(ctx.accounts.pool.key())?;
+
         let pool = &mut ctx.accounts.pool;
         pool.authority = ctx.accounts.authority.key();
         pool.paused = false;
@@ -146,38 +716,117 @@ pub mod farming {
         // pool.reward_b_mint = ctx.accounts.reward_b_mint.key();
         // pool.reward_b_vault = ctx.accounts.reward_b_vault.key();
         pool.reward_duration = reward_duration;
+        pool.current_period_duration = reward_duration;
         pool.total_staked = 0;
         pool.reward_duration_end = 0;
-        pool.total_reward=2100000;
+        pool.staking_decimals = ctx.accounts.staking_mint.decimals;
+        pool.reward_decimals = ctx.accounts.reward_a_mint.decimals;
+        // the reward budget is only what `fund` and `charge_reward` add
+        pool.total_reward = 0;
         let current_time = clock::Clock::get()
                 .unwrap()
                 .unix_timestamp
                 .try_into()
                 .unwrap();
         pool.last_update_time = current_time;
+        pool.checkpoint_time = current_time;
         pool.reward_a_per_token_stored = 0;
         // pool.reward_b_per_token_stored = 0;
         pool.user_stake_count = 0;
         pool.base_key = ctx.accounts.base.key();
         // Unwrap here is safe as long as the key matches the account in the context
         pool.pool_bump = *ctx.bumps.get("pool").unwrap();
+        pool.version = POOL_VERSION;
+
+        let clock = clock::Clock::get()?;
+        emit!(EventInitializePool {
+            pool: pool.key(),
+            authority: pool.authority,
+            staking_mint: pool.staking_mint,
+            reward_a_mint: pool.reward_a_mint,
+            reward_duration,
+            total_reward: pool.total_reward,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Creates or updates the metadata of a pool
+    pub fn set_pool_metadata(
+        ctx: Context<SetPoolMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        validate_pool_metadata(&name, &symbol, &uri, &tags)?;
+        let pool_metadata = &mut ctx.accounts.pool_metadata;
+        if pool_metadata.created_at == 0 {
+            pool_metadata.pool = ctx.accounts.pool.key();
+            pool_metadata.created_at = clock::Clock::get()?
+                .unix_timestamp
+                .try_into()
+                .map_err(|_| ErrorCode::MathOverflow)?;
+            pool_metadata.bump = *ctx.bumps.get("pool_metadata").unwrap();
+        }
+        pool_metadata.name = name;
+        pool_metadata.symbol = symbol;
+        pool_metadata.uri = uri;
+        pool_metadata.tags = tags;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetPoolMetadata {
+            pool: pool_metadata.pool,
+            authority: ctx.accounts.authority.key(),
+            name: pool_metadata.name.clone(),
+            symbol: pool_metadata.symbol.clone(),
+            uri: pool_metadata.uri.clone(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
     /// Initialize a user staking account
     pub fn create_user(ctx: Context<CreateUser>) -> Result<()> {
-        let user = &mut ctx.accounts.user;
-        user.pool = *ctx.accounts.pool.to_account_info().key;
-        user.owner = *ctx.accounts.owner.key;
-        user.reward_a_per_token_complete = 0;
-        // user.reward_b_per_token_complete = 0;
-        user.reward_a_per_token_pending = 0;
-        // user.reward_b_per_token_pending = 0;
-        user.balance_staked = 0;
-        user.nonce = *ctx.bumps.get("user").unwrap();
+        let nonce = *ctx.bumps.get("user").unwrap();
+        init_user(
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.user,
+            ctx.accounts.owner.key(),
+            nonce,
+        )
+    }
 
+    /// Advances the pool reward accumulator, points and epoch accounting to now and emits a snapshot.
+    /// Anyone can call this. User tiers are not affected.
+    pub fn checkpoint(ctx: Context<Checkpoint>) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
-        pool.user_stake_count = pool.user_stake_count.checked_add(1).unwrap();
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        pool.checkpoint(current_time).ok_or(ErrorCode::MathOverflow)?;
+        pool.checkpoint_points(current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.checkpoint_epoch(current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        emit!(EventCheckpoint {
+            pool: pool.key(),
+            caller: ctx.accounts.caller.key(),
+            reward_a_per_token_stored: pool.reward_a_per_token_stored,
+            points_per_token_stored: pool.points_per_token_stored,
+            epoch_stake_seconds: pool.epoch_stake_seconds,
+            reward_a_rate: pool.get_reward_a_rate(),
+            total_staked: pool.total_staked,
+            total_reward: pool.total_reward,
+            total_pending: pool.total_pending,
+            last_update_time: pool.last_update_time,
+            reward_duration_end: pool.reward_duration_end,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
@@ -186,6 +835,13 @@ pub mod farming {
         let pool = &mut ctx.accounts.pool;
         pool.paused = true;
 
+        let clock = clock::Clock::get()?;
+        emit!(EventPause {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
@@ -193,74 +849,483 @@ pub mod farming {
     pub fn unpause(ctx: Context<Unpause>) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         pool.paused = false;
+        let clock = clock::Clock::get()?;
+        emit!(EventUnpause {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    /// User deposit tokens in the pool.
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        if amount == 0 {
-            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+    /// Set the per-operation pause flags. Unlike `pause`, this can be used at any time.
+    pub fn set_pause_flags(ctx: Context<SetPauseFlags>, pause_flags: u8) -> Result<()> {
+        if pause_flags & !PAUSE_ALL != 0 {
+            return Err(ErrorCode::InvalidPauseFlags.into());
         }
         let pool = &mut ctx.accounts.pool;
-        if pool.paused {
-            return Err(ErrorCode::PoolPaused.into());
+        let old_pause_flags = pool.pause_flags;
+        pool.pause_flags = pause_flags;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetPauseFlags {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_pause_flags,
+            new_pause_flags: pause_flags,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Freezes a user account, blocking its deposits and claims. The owner can still withdraw the stake.
+    /// Not timelocked, like the pause flags.
+    pub fn freeze_user(ctx: Context<FreezeUser>) -> Result<()> {
+        let user = &mut ctx.accounts.user;
+        user.frozen = true;
+        let clock = clock::Clock::get()?;
+        emit!(EventFreezeUser {
+            pool: ctx.accounts.pool.key(),
+            user: user.key(),
+            owner: user.owner,
+            authority: ctx.accounts.authority.key(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Unfreezes a user account frozen by `freeze_user`
+    pub fn unfreeze_user(ctx: Context<FreezeUser>) -> Result<()> {
+        let user = &mut ctx.accounts.user;
+        user.frozen = false;
+        let clock = clock::Clock::get()?;
+        emit!(EventUnfreezeUser {
+            pool: ctx.accounts.pool.key(),
+            user: user.key(),
+            owner: user.owner,
+            authority: ctx.accounts.authority.key(),
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets how long deposits must stay staked before they earn rewards. Takes effect for new deposits.
+    pub fn set_min_stake_seconds(ctx: Context<SetMinStakeSeconds>, min_stake_seconds: u64) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetMinStakeSeconds { min_stake_seconds },
+            ctx.accounts.authority.key(),
+        )?;
+        let pool = &mut ctx.accounts.pool;
+        let old_min_stake_seconds = pool.min_stake_seconds;
+        pool.min_stake_seconds = min_stake_seconds;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetMinStakeSeconds {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_min_stake_seconds,
+            new_min_stake_seconds: min_stake_seconds,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets the delay between queueing an admin action and executing it.
+    /// Goes through the current timelock itself, so a timelock can't be lifted at once.
+    pub fn set_timelock_seconds(ctx: Context<SetTimelockSeconds>, timelock_seconds: u64) -> Result<()> {
+        if timelock_seconds > MAX_TIMELOCK_SECONDS {
+            return Err(ErrorCode::TimelockTooLong.into());
         }
-        let user_opt = Some(&mut ctx.accounts.user);
-        update_rewards(pool, user_opt, pool.total_staked).unwrap();
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetTimelockSeconds { timelock_seconds },
+            ctx.accounts.authority.key(),
+        )?;
+        let pool = &mut ctx.accounts.pool;
+        let old_timelock_seconds = pool.timelock_seconds;
+        pool.timelock_seconds = timelock_seconds;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetTimelockSeconds {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_timelock_seconds,
+            new_timelock_seconds: timelock_seconds,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        ctx.accounts.user.balance_staked = ctx
-            .accounts
-            .user
-            .balance_staked
-            .checked_add(amount)
-            .unwrap();
+    /// Sets the share of the staked tokens, in basis points, that `withdraw`, `withdraw_extra_token`
+    /// and `force_close_user` may move out of the pool per `window_seconds`. Outflows that would go
+    /// over it fail. A withdrawal going over it pauses withdrawals until the end of the window instead,
+    /// see `withdraw`. 0 disables the limit.
+    pub fn set_outflow_limit(
+        ctx: Context<SetOutflowLimit>,
+        limit_bps: u16,
+        window_seconds: u64,
+    ) -> Result<()> {
+        if limit_bps > MAX_FEE_BPS || (limit_bps > 0 && window_seconds == 0) {
+            return Err(ErrorCode::InvalidOutflowLimit.into());
+        }
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetOutflowLimit {
+                limit_bps,
+                window_seconds,
+            },
+            ctx.accounts.authority.key(),
+        )?;
+        let pool = &mut ctx.accounts.pool;
+        let old_limit_bps = pool.outflow_limit_bps;
+        let old_window_seconds = pool.outflow_window_seconds;
+        pool.outflow_limit_bps = limit_bps;
+        pool.outflow_window_seconds = window_seconds;
+        // the next outflow starts a fresh window, lifting a tripped limit
+        pool.outflow_window_start = 0;
+        pool.outflow_paused_until = 0;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetOutflowLimit {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_limit_bps,
+            new_limit_bps: limit_bps,
+            old_window_seconds,
+            new_window_seconds: window_seconds,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        // Transfer tokens into the stake vault.
-        {
-            let cpi_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.stake_from_account.to_account_info(),
-                    to: ctx.accounts.staking_vault.to_account_info(),
-                    authority: ctx.accounts.owner.to_account_info(), //todo use user account as signer
-                },
-            );
-            token::transfer(cpi_ctx, amount)?;
-            pool.total_staked = pool
-                .total_staked
-                .checked_add(amount)
+    /// Sets the early exit penalty, in basis points, paid by withdrawals of stake within `window_seconds` of its deposit.
+    /// 0 disables the penalty.
+    pub fn set_early_exit_penalty(
+        ctx: Context<SetEarlyExitPenalty>,
+        penalty_bps: u16,
+        window_seconds: u64,
+    ) -> Result<()> {
+        if penalty_bps > MAX_FEE_BPS {
+            return Err(ErrorCode::InvalidEarlyExitPenalty.into());
+        }
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetEarlyExitPenalty {
+                penalty_bps,
+                window_seconds,
+            },
+            ctx.accounts.authority.key(),
+        )?;
+        let pool = &mut ctx.accounts.pool;
+        let old_penalty_bps = pool.early_exit_penalty_bps;
+        let old_window_seconds = pool.early_exit_window_seconds;
+        pool.early_exit_penalty_bps = penalty_bps;
+        pool.early_exit_window_seconds = window_seconds;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetEarlyExitPenalty {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_penalty_bps,
+            new_penalty_bps: penalty_bps,
+            old_window_seconds,
+            new_window_seconds: window_seconds,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets the curve mapping the pool total staked to its emission rate, so the APR follows the TVL
+    /// instead of sharing a fixed budget. Points are in UI units and stored in base units.
+    /// A curve with no points goes back to the emission schedule.
+    /// Rewards are still capped by the funded budget.
+    pub fn set_utilization_curve(
+        ctx: Context<SetUtilizationCurve>,
+        curve: UtilizationCurve,
+    ) -> Result<()> {
+        if !curve.is_valid() {
+            return Err(ErrorCode::InvalidUtilizationCurve.into());
+        }
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetUtilizationCurve { curve },
+            ctx.accounts.authority.key(),
+        )?;
+        let pool = &mut ctx.accounts.pool;
+        let mut base_curve = curve;
+        for point in base_curve.points.iter_mut() {
+            point.total_staked = pool
+                .staking_to_base_units(point.total_staked.into())
+                .and_then(|amount| amount.try_into().ok())
+                .ok_or(ErrorCode::MathOverflow)?;
+            point.rate = pool
+                .reward_to_base_units(point.rate)
                 .ok_or(ErrorCode::MathOverflow)?;
-            let current_time = clock::Clock::get()
-                .unwrap()
-                .unix_timestamp
-                .try_into()
-                .unwrap();
-            pool.last_update_time=current_time;
-            emit!(EventDeposit { amount });
         }
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        // emission up to now follows the previous curve
+        pool.checkpoint(current_time).ok_or(ErrorCode::MathOverflow)?;
+        let old_curve = pool.utilization_curve;
+        pool.utilization_curve = base_curve;
+        emit!(EventSetUtilizationCurve {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_curve,
+            new_curve: base_curve,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets the points earned per staked token per second. With `points_only` no token rewards
+    /// are credited, so the pool can run a points campaign without funding the reward vault.
+    pub fn set_points_rate(
+        ctx: Context<SetPointsRate>,
+        points_per_token_second: u128,
+        points_only: bool,
+    ) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetPointsRate {
+                points_per_token_second,
+                points_only,
+            },
+            ctx.accounts.authority.key(),
+        )?;
+        let pool = &mut ctx.accounts.pool;
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        // points up to now follow the previous rate
+        pool.checkpoint_points(current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let old_points_per_token_second = pool.points_per_token_second;
+        pool.points_per_token_second = points_per_token_second;
+        pool.points_only = points_only;
+        emit!(EventSetPointsRate {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_points_per_token_second,
+            new_points_per_token_second: points_per_token_second,
+            points_only,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Queues an admin action, executable by its own instruction once the pool timelock has passed.
+    /// `action_id` is picked by the authority to tell apart actions queued at the same time.
+    pub fn queue_action(ctx: Context<QueueAction>, action_id: u64, action: AdminAction) -> Result<()> {
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let queued_action = &mut ctx.accounts.queued_action;
+        queued_action.pool = ctx.accounts.pool.key();
+        queued_action.action_id = action_id;
+        queued_action.action = action;
+        queued_action.queued_at = current_time;
+        queued_action.eta = current_time
+            .checked_add(ctx.accounts.pool.timelock_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        queued_action.bump = *ctx.bumps.get("queued_action").unwrap();
+        emit!(EventQueueAction {
+            pool: queued_action.pool,
+            authority: ctx.accounts.authority.key(),
+            queued_action: queued_action.key(),
+            action_id,
+            action,
+            eta: queued_action.eta,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Cancels a queued admin action before it is executed, refunding its rent to the authority.
+    pub fn cancel_action(ctx: Context<CancelAction>) -> Result<()> {
+        let queued_action = &ctx.accounts.queued_action;
+        let clock = clock::Clock::get()?;
+        emit!(EventCancelAction {
+            pool: queued_action.pool,
+            authority: ctx.accounts.authority.key(),
+            queued_action: queued_action.key(),
+            action_id: queued_action.action_id,
+            action: queued_action.action,
+            eta: queued_action.eta,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
+    /// User deposit tokens in the pool.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        stake_tokens(
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.user,
+            &ctx.accounts.stake_from_account,
+            &ctx.accounts.staking_vault,
+            &ctx.accounts.owner,
+            &ctx.accounts.token_program,
+            amount,
+        )
+    }
+
+    /// User deposit tokens in the pool, creating the user staking account on the first deposit.
+    pub fn deposit_with_init(ctx: Context<DepositWithInit>, amount: u64) -> Result<()> {
+        let nonce = *ctx.bumps.get("user").unwrap();
+        let mut user = load_or_init_user(
+            &mut ctx.accounts.pool,
+            &ctx.accounts.user,
+            ctx.accounts.owner.key(),
+            nonce,
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+        )?;
+        stake_tokens(
+            &mut ctx.accounts.pool,
+            &mut user,
+            &ctx.accounts.stake_from_account,
+            &ctx.accounts.staking_vault,
+            &ctx.accounts.owner,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        user.exit(&crate::ID)
+    }
+
+    /// Payer deposits tokens in the pool on behalf of `beneficiary`, creating the beneficiary's
+    /// user staking account if needed. Only the beneficiary can withdraw the stake.
+    /// The deposit matures and leaves the early exit window on its own, the beneficiary's windows are not restarted.
+    pub fn deposit_for(ctx: Context<DepositFor>, amount: u64) -> Result<()> {
+        // a frozen wallet cannot stake through someone else's position either
+        if is_frozen_user(&ctx.accounts.payer_user)? {
+            return Err(ErrorCode::UserFrozen.into());
+        }
+        let nonce = *ctx.bumps.get("user").unwrap();
+        let mut user = load_or_init_user(
+            &mut ctx.accounts.pool,
+            &ctx.accounts.user,
+            ctx.accounts.beneficiary.key(),
+            nonce,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+        stake_tokens(
+            &mut ctx.accounts.pool,
+            &mut user,
+            &ctx.accounts.stake_from_account,
+            &ctx.accounts.staking_vault,
+            &ctx.accounts.payer,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        user.exit(&crate::ID)
+    }
+
     /// User withdraw tokens in the pool.
-    pub fn withdraw(ctx: Context<Deposit>, spt_amount: u64) -> Result<()> {
+    /// Withdrawals of stake deposited within the early exit window pay the early exit penalty into the reward pool.
+    /// It comes out of the principal when the staking and reward mints match, out of the pending rewards
+    /// on that stake otherwise, which claims keep back until the window ends.
+    /// `reward_a_vault` is optional and only required when the penalty comes out of the principal.
+    /// A withdrawal going over the pool outflow limit pauses withdrawals until the end of the outflow window
+    /// and transfers nothing.
+    pub fn withdraw(ctx: Context<Withdraw>, spt_amount: u64) -> Result<()> {
         if spt_amount == 0 {
             return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
         }
 
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
         let pool = &mut ctx.accounts.pool;
+        if pool.is_withdraw_paused(current_time) {
+            return Err(ErrorCode::WithdrawPaused.into());
+        }
 
         if ctx.accounts.user.balance_staked < spt_amount {
             return Err(ErrorCode::InsufficientFundWithdraw.into());
         }
+        // the pause must stick, so a tripping withdrawal succeeds without transferring anything
+        if !record_withdraw_outflow(pool, ctx.accounts.owner.key(), spt_amount, current_time)? {
+            return Ok(());
+        }
 
+        let balance_before = ctx.accounts.user.balance_staked;
         let user_opt = Some(&mut ctx.accounts.user);
         update_rewards(pool, user_opt, pool.total_staked).unwrap();
-        ctx.accounts.user.balance_staked = ctx
+        let withheld_before = ctx
             .accounts
             .user
-            .balance_staked
+            .early_exit_withheld(pool.early_exit_window_seconds, current_time);
+        // withdrawals come out of the stake past the early exit window first
+        let (early_amount, withheld_taken) = ctx
+            .accounts
+            .user
+            .take_early_exit_lots(spt_amount, pool.early_exit_window_seconds, current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.user.balance_staked = ctx
+            .accounts
+            .user
+            .balance_staked
             .checked_sub(spt_amount)
             .unwrap();
+        // withdrawals come out of the immature stake first
+        ctx.accounts.user.immature_balance =
+            ctx.accounts.user.immature_balance.saturating_sub(spt_amount);
+
+        let penalty_from_principal = pool.staking_mint == pool.reward_a_mint;
+        let penalized_amount = if penalty_from_principal {
+            early_amount
+        } else {
+            // the rewards withheld for the withdrawn early stake, and its share of the rest
+            let pending = ctx.accounts.user.reward_a_per_token_pending;
+            let earned = u128::from(pending.saturating_sub(withheld_before))
+                .checked_mul(early_amount.into())
+                .and_then(|v| v.checked_div(balance_before.into()))
+                .and_then(|v| u64::try_from(v).ok())
+                .and_then(|v| v.checked_add(withheld_taken))
+                .ok_or(ErrorCode::MathOverflow)?;
+            std::cmp::min(earned, pending)
+        };
+        let penalty = pool
+            .early_exit_penalty(penalized_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if !penalty_from_principal {
+            // the forfeited rewards are already in the reward vault
+            ctx.accounts.user.reward_a_per_token_pending = ctx
+                .accounts
+                .user
+                .reward_a_per_token_pending
+                .checked_sub(penalty)
+                .ok_or(ErrorCode::MathOverflow)?;
+            pool.total_pending = pool.total_pending.saturating_sub(penalty);
+        }
+        pool.total_reward = pool
+            .total_reward
+            .checked_add(penalty)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         // Transfer tokens from the pool vault to user vault.
         {
@@ -284,28 +1349,68 @@ pub mod farming {
                 },
                 pool_signer,
             );
-            token::transfer(cpi_ctx, spt_amount)?;
+            let principal_penalty = if penalty_from_principal { penalty } else { 0 };
+            token::transfer(
+                cpi_ctx,
+                spt_amount
+                    .checked_sub(principal_penalty)
+                    .ok_or(ErrorCode::MathOverflow)?,
+            )?;
+            if principal_penalty > 0 {
+                let reward_a_vault = ctx
+                    .accounts
+                    .reward_a_vault
+                    .as_ref()
+                    .ok_or(ErrorCode::RewardVaultRequired)?;
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.staking_vault.to_account_info(),
+                        to: reward_a_vault.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    pool_signer,
+                );
+                token::transfer(cpi_ctx, principal_penalty)?;
+            }
 
             pool.total_staked = pool
                 .total_staked
                 .checked_sub(spt_amount)
                 .ok_or(ErrorCode::MathOverflow)?;
-            let current_time = clock::Clock::get()
-                .unwrap()
-                .unix_timestamp
-                .try_into()
-                .unwrap();
             pool.last_update_time=current_time;
-            emit!(EventWithdraw { amount: spt_amount });
+            let clock = clock::Clock::get()?;
+            emit!(EventWithdraw {
+                pool: pool.key(),
+                owner: ctx.accounts.owner.key(),
+                amount: spt_amount,
+                penalty,
+                balance_before,
+                balance_after: ctx.accounts.user.balance_staked,
+                reward_pending: ctx.accounts.user.reward_a_per_token_pending,
+                total_staked: pool.total_staked,
+                total_reward: pool.total_reward,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+            });
         }
         Ok(())
     }
 
     /// Authorize additional funders for the pool
     pub fn authorize_funder(ctx: Context<FunderChange>, funder_to_add: Pubkey) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::AuthorizeFunder {
+                funder: funder_to_add,
+            },
+            ctx.accounts.authority.key(),
+        )?;
         if funder_to_add == ctx.accounts.pool.authority.key() {
             return Err(ErrorCode::FunderAlreadyAuthorized.into());
         }
+        let pool = ctx.accounts.pool.key();
         let funders = &mut ctx.accounts.pool.funders;
         if funders.iter().any(|x| *x == funder_to_add) {
             return Err(ErrorCode::FunderAlreadyAuthorized.into());
@@ -313,8 +1418,13 @@ pub mod farming {
         let default_pubkey = Pubkey::default();
         if let Some(idx) = funders.iter().position(|x| *x == default_pubkey) {
             funders[idx] = funder_to_add;
+            let clock = clock::Clock::get()?;
             emit!(EventAuthorizeFunder {
-                new_funder: funder_to_add
+                pool,
+                authority: ctx.accounts.authority.key(),
+                new_funder: funder_to_add,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
             });
         } else {
             return Err(ErrorCode::MaxFunders.into());
@@ -324,14 +1434,28 @@ pub mod farming {
 
     /// Deauthorize funders for the pool
     pub fn deauthorize_funder(ctx: Context<FunderChange>, funder_to_remove: Pubkey) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::DeauthorizeFunder {
+                funder: funder_to_remove,
+            },
+            ctx.accounts.authority.key(),
+        )?;
         if funder_to_remove == ctx.accounts.pool.authority.key() {
             return Err(ErrorCode::CannotDeauthorizePoolAuthority.into());
         }
+        let pool = ctx.accounts.pool.key();
         let funders = &mut ctx.accounts.pool.funders;
         if let Some(idx) = funders.iter().position(|x| *x == funder_to_remove) {
             funders[idx] = Pubkey::default();
+            let clock = clock::Clock::get()?;
             emit!(EventUnauthorizeFunder {
-                funder: funder_to_remove
+                pool,
+                authority: ctx.accounts.authority.key(),
+                funder: funder_to_remove,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
             });
         } else {
             return Err(ErrorCode::CannotDeauthorizeMissingAuthority.into());
@@ -339,9 +1463,86 @@ pub mod farming {
         Ok(())
     }
 
+    /// Append a future segment to the pool emission schedule. Each segment may halve its own rate every `halving_interval` seconds.
+    /// The rate is in UI units of the reward token per second, scaled by PRECISION.
+    pub fn append_emission_segment(
+        ctx: Context<UpdateEmission>,
+        start_time: u64,
+        rate: u128,
+        halving_interval: u64,
+    ) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::AppendEmissionSegment {
+                start_time,
+                rate,
+                halving_interval,
+            },
+            ctx.accounts.authority.key(),
+        )?;
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        if start_time <= current_time {
+            return Err(ErrorCode::EmissionSegmentNotInFuture.into());
+        }
+        let pool = ctx.accounts.pool.key();
+        // the rate is given in UI units
+        let base_rate = ctx
+            .accounts
+            .pool
+            .reward_to_base_units(rate)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let segments = &mut ctx.accounts.pool.emission_segments;
+        if segments.iter().any(|s| s.start_time >= start_time) {
+            return Err(ErrorCode::EmissionSegmentOutOfOrder.into());
+        }
+        if let Some(idx) = segments.iter().position(|s| s.start_time == 0) {
+            segments[idx] = EmissionSegment {
+                start_time,
+                rate: base_rate,
+                halving_interval,
+            };
+            let clock = clock::Clock::get()?;
+            emit!(EventAppendEmissionSegment {
+                pool,
+                authority: ctx.accounts.authority.key(),
+                start_time,
+                rate,
+                halving_interval,
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+            });
+        } else {
+            return Err(ErrorCode::MaxEmissionSegments.into());
+        }
+        Ok(())
+    }
+
     /// Fund the pool with rewards.  This resets the clock on the end date, pushing it out to the set duration. And, linearly redistributes remaining rewards.
-    pub fn fund(ctx: Context<Fund>, amount_a: u64) -> Result<()> {
+    /// An optional `start_time` schedules the reward period to begin in the future; nothing accrues before it.
+    pub fn fund(ctx: Context<Fund>, amount_a: u64, start_time: Option<u64>) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
+        let current_time: u64 = clock::Clock::get()
+            .unwrap()
+            .unix_timestamp
+            .try_into()
+            .unwrap();
+        let start_time = match start_time {
+            Some(start_time) => {
+                if start_time < current_time {
+                    return Err(ErrorCode::RewardStartTimeInPast.into());
+                }
+                if pool.reward_start_time <= current_time && current_time < pool.reward_duration_end {
+                    return Err(ErrorCode::RewardPeriodInProgress.into());
+                }
+                start_time
+            }
+            // keep a period that has not started yet at its announced start
+            None => std::cmp::max(current_time, pool.reward_start_time),
+        };
 
         //can't compare using reward_vault because it is PDA. The PDA seed contain different prefix
         //if mint are the same, we just use a
@@ -350,6 +1551,7 @@ pub mod farming {
         // }
 
         update_rewards(pool, None, pool.total_staked).unwrap();
+        let total_reward_before = pool.total_reward;
 
         let reward_a_rate = rate_after_funding(pool, amount_a)?;
         pool.reward_a_rate_u128 = reward_a_rate;
@@ -377,115 +1579,378 @@ pub mod farming {
             token::transfer(cpi_ctx, amount_a)?;
         }
 
-        let current_time = clock::Clock::get()
-            .unwrap()
-            .unix_timestamp
-            .try_into()
-            .unwrap();
         pool.last_update_time = current_time;
-        pool.reward_duration_end = current_time.checked_add(pool.reward_duration).unwrap();
+        pool.reward_start_time = start_time;
+        pool.reward_duration_end = start_time
+            .checked_add(pool.period_duration())
+            .ok_or(ErrorCode::MathOverflow)?;
         pool.total_reward=pool.total_reward.checked_add(amount_a).unwrap();
 
-        emit!(EventFund { amount_a});
+        let clock = clock::Clock::get()?;
+        emit!(EventFund {
+            pool: pool.key(),
+            funder: ctx.accounts.funder.key(),
+            amount_a,
+            start_time,
+            reward_duration_end: pool.reward_duration_end,
+            reward_a_rate,
+            total_reward_before,
+            total_reward: pool.total_reward,
+            total_staked: pool.total_staked,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    /// User claim rewards
-    pub fn claim(ctx: Context<ClaimReward>) -> Result<()> {
+    /// Starts epoch `epoch_number`, the one after the last started epoch, and funds its budget.
+    /// The previous epoch must have been finalized.
+    pub fn start_epoch(
+        ctx: Context<StartEpoch>,
+        epoch_number: u64,
+        start_time: u64,
+        end_time: u64,
+        budget: u64,
+    ) -> Result<()> {
+        if budget == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        if start_time < current_time || end_time <= start_time {
+            return Err(ErrorCode::InvalidEpochWindow.into());
+        }
         let pool = &mut ctx.accounts.pool;
-        let current_time = clock::Clock::get()
-            .unwrap()
+        if epoch_number != pool.epoch_count.checked_add(1).ok_or(ErrorCode::MathOverflow)? {
+            return Err(ErrorCode::InvalidEpoch.into());
+        }
+        if pool.epoch_count > 0 && !pool.epoch_finalized {
+            return Err(ErrorCode::EpochNotFinalized.into());
+        }
+        update_rewards(pool, None, pool.total_staked)?;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.from_a.to_account_info(),
+                to: ctx.accounts.reward_a_vault.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, budget)?;
+
+        pool.push_epoch(EpochWindow {
+            epoch: epoch_number,
+            start_time,
+            end_time,
+        })
+        .ok_or(ErrorCode::MathOverflow)?;
+        pool.epoch_reserved = pool
+            .epoch_reserved
+            .checked_add(budget)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let epoch = &mut ctx.accounts.epoch;
+        epoch.pool = pool.key();
+        epoch.epoch = epoch_number;
+        epoch.budget = budget;
+        epoch.start_time = start_time;
+        epoch.end_time = end_time;
+        epoch.bump = *ctx.bumps.get("epoch").unwrap();
+
+        emit!(EventStartEpoch {
+            pool: pool.key(),
+            funder: ctx.accounts.funder.key(),
+            epoch: epoch_number,
+            budget,
+            start_time,
+            end_time,
+            epoch_reserved: pool.epoch_reserved,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Records the total stake-seconds of the current epoch once it has ended, which opens its claims.
+    /// The budget of an epoch nobody staked in is released. Anyone can call this.
+    pub fn finalize_epoch(ctx: Context<FinalizeEpoch>) -> Result<()> {
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
             .unix_timestamp
             .try_into()
-            .unwrap();
-        
-        let user_opt = Some(&mut ctx.accounts.user);
-        update_rewards(pool, user_opt, pool.total_staked).unwrap();
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let epoch = &mut ctx.accounts.epoch;
+        if current_time < epoch.end_time {
+            return Err(ErrorCode::EpochNotEnded.into());
+        }
+        let pool = &mut ctx.accounts.pool;
+        update_rewards(pool, None, pool.total_staked)?;
+
+        epoch.total_stake_seconds = pool.epoch_stake_seconds;
+        epoch.finalized = true;
+        pool.epoch_finalized = true;
+        if epoch.total_stake_seconds == 0 {
+            pool.epoch_reserved = pool.epoch_reserved.saturating_sub(epoch.budget);
+            epoch.claimed = epoch.budget;
+        }
 
-        pool.last_update_time=current_time;
+        emit!(EventFinalizeEpoch {
+            pool: pool.key(),
+            caller: ctx.accounts.caller.key(),
+            epoch: epoch.epoch,
+            budget: epoch.budget,
+            total_stake_seconds: epoch.total_stake_seconds,
+            epoch_reserved: pool.epoch_reserved,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        let reward_duration = ctx.accounts.pool.reward_duration.to_be_bytes();
-        let seeds = &[
-            reward_duration.as_ref(),
-            ctx.accounts.pool.staking_mint.as_ref(),
-            ctx.accounts.pool.reward_a_mint.as_ref(),
-            // ctx.accounts.pool.reward_b_mint.as_ref(),
-            ctx.accounts.pool.base_key.as_ref(),
-            &[ctx.accounts.pool.pool_bump],
-        ];
-        let pool_signer = &[&seeds[..]];
+    /// Change the length of reward periods. A running or scheduled period is stretched or shrunk
+    /// to the new duration and its leftover rewards are linearly redistributed, like `fund` does.
+    pub fn set_reward_duration(ctx: Context<SetRewardDuration>, duration: u64) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::SetRewardDuration { duration },
+            ctx.accounts.authority.key(),
+        )?;
+        if duration < MIN_DURATION {
+            return Err(ErrorCode::DurationTooShort.into());
+        }
+        let pool = &mut ctx.accounts.pool;
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+
+        update_rewards(pool, None, pool.total_staked)?;
+
+        let old_duration = pool.period_duration();
+        pool.current_period_duration = duration;
+        if current_time < pool.reward_duration_end {
+            let reward_a_rate = rate_after_funding(pool, 0)?;
+            pool.reward_a_rate_u128 = reward_a_rate;
+            if let Ok(reward_rate) = u64::try_from(reward_a_rate) {
+                pool._reward_a_rate = reward_rate;
+            }
+            pool.reward_duration_end = std::cmp::max(current_time, pool.reward_start_time)
+                .checked_add(duration)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
 
-        let mut claimed_reward_a: u64 = 0;
-        // let mut claimed_reward_b: u64 = 0;
+        let clock = clock::Clock::get()?;
+        emit!(EventSetRewardDuration {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            old_duration,
+            new_duration: duration,
+            reward_duration_end: pool.reward_duration_end,
+            reward_a_rate: pool.reward_a_rate_u128,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        if ctx.accounts.user.reward_a_per_token_pending > 0 {
-            let mut reward_amount = ctx.accounts.user.reward_a_per_token_pending;
-            let vault_balance = ctx.accounts.reward_a_vault.amount;
+    /// User claim rewards
+    pub fn claim(ctx: Context<ClaimReward>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        claim_rewards(
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.user,
+            &ctx.accounts.reward_a_vault,
+            &ctx.accounts.reward_a_account,
+            owner,
+            &ctx.accounts.token_program,
+        )
+    }
 
-            ctx.accounts.user.reward_a_per_token_pending = 0;
-            if vault_balance < reward_amount {
-                reward_amount = vault_balance;
+    /// User claims rewards from several pools at once. `remaining_accounts` holds one group of
+    /// pool, user, reward A vault and reward A destination per pool, validated like `claim`.
+    pub fn claim_many<'info>(ctx: Context<'_, '_, '_, 'info, ClaimMany<'info>>) -> Result<()> {
+        let groups = ctx.remaining_accounts.chunks_exact(CLAIM_MANY_GROUP_LEN);
+        if ctx.remaining_accounts.is_empty() || !groups.remainder().is_empty() {
+            return Err(ErrorCode::InvalidRemainingAccounts.into());
+        }
+        let owner = ctx.accounts.owner.key();
+        for group in groups {
+            if group.iter().any(|info| !info.is_writable) {
+                return Err(error!(anchor_lang::error::ErrorCode::ConstraintMut));
             }
+            let mut pool: Box<Account<Pool>> = Box::new(Account::try_from(&group[0])?);
+            let mut user: Box<Account<User>> = Box::new(Account::try_from(&group[1])?);
+            let reward_a_vault: Account<TokenAccount> = Account::try_from(&group[2])?;
+            let reward_a_account: Account<TokenAccount> = Account::try_from(&group[3])?;
 
-            if reward_amount > 0 {
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    token::Transfer {
-                        from: ctx.accounts.reward_a_vault.to_account_info(),
-                        to: ctx.accounts.reward_a_account.to_account_info(),
-                        authority: ctx.accounts.pool.to_account_info(),
-                    },
-                    pool_signer,
-                );
-                token::transfer(cpi_ctx, reward_amount)?;
-                claimed_reward_a = reward_amount;
+            if pool.reward_a_vault != reward_a_vault.key()
+                || user.pool != pool.key()
+                || user.owner != owner
+            {
+                return Err(error!(anchor_lang::error::ErrorCode::ConstraintHasOne));
+            }
+            let user_address = Pubkey::create_program_address(
+                &[owner.as_ref(), pool.key().as_ref(), &[user.nonce]],
+                ctx.program_id,
+            )
+            .map_err(|_| error!(anchor_lang::error::ErrorCode::ConstraintSeeds))?;
+            if user_address != user.key() {
+                return Err(error!(anchor_lang::error::ErrorCode::ConstraintSeeds));
             }
+            if reward_a_account.mint != pool.reward_a_mint {
+                return Err(ErrorCode::InvalidRewardMint.into());
+            }
+            if reward_a_account.owner != owner {
+                return Err(ErrorCode::InvalidTokenAccountOwner.into());
+            }
+
+            claim_rewards(
+                &mut pool,
+                &mut user,
+                &reward_a_vault,
+                &reward_a_account,
+                owner,
+                &ctx.accounts.token_program,
+            )?;
+            // persist before a later group reloads the same accounts
+            pool.exit(ctx.program_id)?;
+            user.exit(ctx.program_id)?;
         }
-        // let current_time:u64 = clock::Clock::get()
-        //     .unwrap()
-        //     .unix_timestamp
-        //     .try_into()
-        //     .unwrap();
-        // pool.last_update_time=current_time;
-        // if ctx.accounts.user.reward_b_per_token_pending > 0 {
-            // let mut reward_amount = ctx.accounts.user.reward_b_per_token_pending;
-            // let vault_balance = ctx.accounts.reward_b_vault.amount;
-
-            // ctx.accounts.user.reward_b_per_token_pending = 0;
-            // if vault_balance < reward_amount {
-            //     reward_amount = vault_balance;
-            // }
-
-            // if reward_amount > 0 {
-            //     let cpi_ctx = CpiContext::new_with_signer(
-            //         ctx.accounts.token_program.to_account_info(),
-            //         token::Transfer {
-            //             from: ctx.accounts.reward_b_vault.to_account_info(),
-            //             to: ctx.accounts.reward_b_account.to_account_info(),
-            //             authority: ctx.accounts.pool.to_account_info(),
-            //         },
-            //         pool_signer,
-            //     );
-            //     token::transfer(cpi_ctx, reward_amount)?;
-            //     claimed_reward_b = reward_amount;
-            // }
-        // }
-            
-        emit!(EventClaim {
-            amount_a: claimed_reward_a,
-            // amount_b: claimed_reward_b
+        Ok(())
+    }
+
+    /// User claims their share of a finalized epoch budget, by stake-seconds, within `EPOCH_CLAIM_PERIOD` of its end.
+    /// Stake-seconds are kept for the last `EPOCH_HISTORY` epochs the user held a stake in.
+    pub fn claim_epoch(ctx: Context<ClaimEpoch>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let user = &mut ctx.accounts.user;
+        if pool.is_paused(PAUSE_CLAIM) {
+            return Err(ErrorCode::ClaimPaused.into());
+        }
+        if user.frozen {
+            return Err(ErrorCode::UserFrozen.into());
+        }
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        if ctx.accounts.epoch.is_claim_period_over(current_time) {
+            return Err(ErrorCode::EpochClaimPeriodOver.into());
+        }
+        // records the stake-seconds held since the last update
+        update_rewards(pool, Some(user), pool.total_staked)?;
+
+        let epoch = &mut ctx.accounts.epoch;
+        let record = user
+            .epoch_stakes
+            .iter_mut()
+            .find(|r| r.epoch == epoch.epoch && r.stake_seconds > 0)
+            .ok_or(ErrorCode::NoEpochStake)?;
+        if record.claimed {
+            return Err(ErrorCode::EpochAlreadyClaimed.into());
+        }
+        record.claimed = true;
+        let stake_seconds = record.stake_seconds;
+        let amount = epoch_reward(epoch.budget, stake_seconds, epoch.total_stake_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        epoch.claimed = epoch
+            .claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.epoch_reserved = pool.epoch_reserved.saturating_sub(amount);
+
+        if amount > 0 {
+            let reward_duration = pool.reward_duration.to_be_bytes();
+            let seeds = &[
+                reward_duration.as_ref(),
+                pool.staking_mint.as_ref(),
+                pool.reward_a_mint.as_ref(),
+                pool.base_key.as_ref(),
+                &[pool.pool_bump],
+            ];
+            let pool_signer = &[&seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.reward_a_vault.to_account_info(),
+                    to: ctx.accounts.reward_a_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        let clock = clock::Clock::get()?;
+        emit!(EventClaimEpoch {
+            pool: pool.key(),
+            owner: user.owner,
+            epoch: epoch.epoch,
+            amount,
+            stake_seconds,
+            total_stake_seconds: epoch.total_stake_seconds,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Releases what is left of a finalized epoch budget once its claim period is over: the shares
+    /// nobody claimed and the rounding dust. They become unallocated rewards. Anyone can call this.
+    pub fn sweep_epoch(ctx: Context<SweepEpoch>) -> Result<()> {
+        let clock = clock::Clock::get()?;
+        let current_time: u64 = clock
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let epoch = &mut ctx.accounts.epoch;
+        if !epoch.is_claim_period_over(current_time) {
+            return Err(ErrorCode::EpochClaimPeriodNotOver.into());
+        }
+        let pool = &mut ctx.accounts.pool;
+        let released = epoch.budget.saturating_sub(epoch.claimed);
+        epoch.claimed = epoch.budget;
+        pool.epoch_reserved = pool.epoch_reserved.saturating_sub(released);
+
+        emit!(EventSweepEpoch {
+            pool: pool.key(),
+            caller: ctx.accounts.caller.key(),
+            epoch: epoch.epoch,
+            released,
+            epoch_reserved: pool.epoch_reserved,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
         });
         Ok(())
     }
 
     /// Withdraw token that mistakenly deposited to staking_vault
     pub fn withdraw_extra_token(ctx: Context<WithdrawExtraToken>) -> Result<()> {
-        let pool = &ctx.accounts.pool;
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::WithdrawExtraToken,
+            ctx.accounts.authority.key(),
+        )?;
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let pool = &mut ctx.accounts.pool;
+        if pool.is_withdraw_paused(current_time) {
+            return Err(ErrorCode::WithdrawPaused.into());
+        }
         let total_amount = ctx.accounts.staking_vault.amount;
         let total_staked = pool.total_staked;
         let withdrawable_amount = total_amount
             .checked_sub(total_staked)
             .ok_or(ErrorCode::MathOverflow)?;
+        check_outflow(pool, withdrawable_amount, current_time)?;
 
         if withdrawable_amount > 0 {
             let reward_duration = pool.reward_duration.to_be_bytes();
@@ -511,6 +1976,85 @@ pub mod farming {
             token::transfer(cpi_ctx, withdrawable_amount)?;
         }
 
+        let clock = clock::Clock::get()?;
+        emit!(EventWithdrawExtraToken {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            amount: withdrawable_amount,
+            vault_balance_before: total_amount,
+            vault_balance_after: total_staked,
+            total_staked,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Withdraw reward tokens that no user is owed once the farm has ended.
+    /// Users keep their pending rewards and whatever can still accrue up to `reward_duration_end`.
+    /// The budget is cut down to that, so the accrual that would otherwise go on past the end stops.
+    pub fn withdraw_unallocated_rewards(ctx: Context<WithdrawUnallocatedRewards>) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::WithdrawUnallocatedRewards,
+            ctx.accounts.authority.key(),
+        )?;
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        let pool = &mut ctx.accounts.pool;
+        let accruable = pool
+            .accruable_rewards(current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let owed = pool
+            .total_pending
+            .checked_add(accruable)
+            .and_then(|owed| owed.checked_add(pool.epoch_reserved))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let withdrawable_amount = ctx.accounts.reward_a_vault.amount.saturating_sub(owed);
+
+        // the budget left for users is now exactly what they can still accrue
+        let total_reward_before = pool.total_reward;
+        pool.total_reward = accruable;
+
+        if withdrawable_amount > 0 {
+            let reward_duration = pool.reward_duration.to_be_bytes();
+            let seeds = &[
+                reward_duration.as_ref(),
+                pool.staking_mint.as_ref(),
+                pool.reward_a_mint.as_ref(),
+                pool.base_key.as_ref(),
+                &[pool.pool_bump],
+            ];
+            let pool_signer = &[&seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.reward_a_vault.to_account_info(),
+                    to: ctx.accounts.withdraw_to_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                pool_signer,
+            );
+
+            token::transfer(cpi_ctx, withdrawable_amount)?;
+        }
+
+        let clock = clock::Clock::get()?;
+        emit!(EventWithdrawUnallocatedRewards {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            amount: withdrawable_amount,
+            total_pending: pool.total_pending,
+            accruable,
+            total_reward_before,
+            total_reward: pool.total_reward,
+            total_staked: pool.total_staked,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
@@ -518,52 +2062,259 @@ pub mod farming {
     pub fn close_user(ctx: Context<CloseUser>) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         pool.user_stake_count = pool.user_stake_count.checked_sub(1).unwrap();
+        let clock = clock::Clock::get()?;
+        emit!(EventCloseUser {
+            pool: pool.key(),
+            owner: ctx.accounts.owner.key(),
+            user: ctx.accounts.user.key(),
+            user_stake_count: pool.user_stake_count,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    /// anyone can call this
-    pub fn migrate_farming_rate(ctx: Context<MigrateFarmingRate>) -> Result<()> {
+    /// Closes an abandoned user stake account once the farm has ended and the grace period has passed.
+    /// The stake and pending rewards are returned to the owner's ATAs and the rent to the owner.
+    /// The pending rewards of a frozen user are forfeited to the reward budget instead.
+    pub fn force_close_user(ctx: Context<ForceCloseUser>) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::ForceCloseUser {
+                user: ctx.accounts.user.key(),
+            },
+            ctx.accounts.authority.key(),
+        )?;
         let pool = &mut ctx.accounts.pool;
-        if pool.reward_a_rate_u128 == 0 && pool._reward_a_rate != 0 {
-            pool.reward_a_rate_u128 = pool._reward_a_rate.into();
-        }
-        // if pool.reward_b_rate_u128 == 0 && pool._reward_b_rate != 0 {
-        //     pool.reward_b_rate_u128 = pool._reward_b_rate.into();
-        // }
-        Ok(())
-    }
+        let user_opt = Some(&mut ctx.accounts.user);
+        update_rewards(pool, user_opt, pool.total_staked)?;
 
-    /// Closes a pool account. Only able to be done when there are no users staked.
-    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
-        let pool = &ctx.accounts.pool;
-        let reward_duration = ctx.accounts.pool.reward_duration.to_be_bytes();
-        let signer_seeds = &[
+        let staked_amount = ctx.accounts.user.balance_staked;
+        let current_time: u64 = clock::Clock::get()?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| ErrorCode::MathOverflow)?;
+        check_outflow(pool, staked_amount, current_time)?;
+        let pending_amount = ctx.accounts.user.reward_a_per_token_pending;
+        let available = ctx
+            .accounts
+            .reward_a_vault
+            .amount
+            .saturating_sub(pool.epoch_reserved);
+        let (reward_amount, reward_forfeited, reward_shortfall) =
+            ctx.accounts.user.force_close_rewards(available);
+        ctx.accounts.user.balance_staked = 0;
+        ctx.accounts.user.reward_a_per_token_pending = 0;
+        pool.total_staked = pool
+            .total_staked
+            .checked_sub(staked_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.total_pending = pool.total_pending.saturating_sub(pending_amount);
+        pool.total_reward = pool
+            .total_reward
+            .checked_add(reward_forfeited)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.user_stake_count = pool
+            .user_stake_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let reward_duration = pool.reward_duration.to_be_bytes();
+        let seeds = &[
             reward_duration.as_ref(),
-            ctx.accounts.pool.staking_mint.as_ref(),
-            ctx.accounts.pool.reward_a_mint.as_ref(),
-            // ctx.accounts.pool.reward_b_mint.as_ref(),
-            ctx.accounts.pool.base_key.as_ref(),
-            &[ctx.accounts.pool.pool_bump],
+            pool.staking_mint.as_ref(),
+            pool.reward_a_mint.as_ref(),
+            pool.base_key.as_ref(),
+            &[pool.pool_bump],
         ];
+        let pool_signer = &[&seeds[..]];
 
-        //instead of closing these vaults, we could technically just
-        //set_authority on them. it's not very ata clean, but it'd work
-        //if size of tx is an issue, thats an approach
-
-        //close staking vault
-        let ix = spl_token::instruction::transfer(
-            &spl_token::ID,
-            ctx.accounts.staking_vault.to_account_info().key,
-            ctx.accounts.staking_refundee.to_account_info().key,
-            &ctx.accounts.pool.key(),
-            &[&ctx.accounts.pool.key()],
-            ctx.accounts.staking_vault.amount,
-        )?;
-        solana_program::program::invoke_signed(
-            &ix,
-            &[
+        if staked_amount > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.staking_vault.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.staking_vault.to_account_info(),
+                    to: ctx.accounts.owner_staking_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, staked_amount)?;
+        }
+        if reward_amount > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.reward_a_vault.to_account_info(),
+                    to: ctx.accounts.owner_reward_a_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, reward_amount)?;
+        }
+
+        let clock = clock::Clock::get()?;
+        emit!(EventForceCloseUser {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            owner: ctx.accounts.owner.key(),
+            staked_amount,
+            reward_amount,
+            reward_forfeited,
+            reward_shortfall,
+            user_stake_count: pool.user_stake_count,
+            total_staked: pool.total_staked,
+            total_reward: pool.total_reward,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Grows a pool account to the current layout and fills in the fields of each newer version,
+    /// reading the decimals of pools that predate them from the pool mints.
+    /// anyone can call this, the payer covers the extra rent
+    pub fn migrate_pool(ctx: Context<MigrateAccount>) -> Result<()> {
+        let account = ctx.accounts.account.to_account_info();
+        grow_account(
+            &account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + Pool::LEN,
+        )?;
+        let mut pool = Pool::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+        if pool.staking_mint != ctx.accounts.staking_mint.key() {
+            return Err(ErrorCode::InvalidStakingMint.into());
+        }
+        if pool.reward_a_mint != ctx.accounts.reward_a_mint.key() {
+            return Err(ErrorCode::InvalidRewardMint.into());
+        }
+        let old_version = pool.version;
+        pool.migrate(
+            ctx.accounts.staking_mint.decimals,
+            ctx.accounts.reward_a_mint.decimals,
+        );
+        let mut data = account.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        pool.try_serialize(&mut writer)?;
+        let clock = clock::Clock::get()?;
+        emit!(EventMigrate {
+            pool: account.key(),
+            account: account.key(),
+            payer: ctx.accounts.payer.key(),
+            old_version,
+            new_version: pool.version,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Copies the u64 reward rate of a pool into `reward_a_rate_u128`.
+    /// Deprecated, `migrate_pool` does the same as part of the pool migration.
+    pub fn migrate_farming_rate(ctx: Context<MigrateFarmingRate>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        if pool.reward_a_rate_u128 == 0 && pool._reward_a_rate != 0 {
+            pool.reward_a_rate_u128 = pool._reward_a_rate.into();
+        }
+        Ok(())
+    }
+
+    /// Grows a user account to the current layout and fills in the fields of each newer version.
+    /// The pending rewards of users predating total_pending are counted in, the pool must be migrated first.
+    /// anyone can call this, the payer covers the extra rent
+    pub fn migrate_user(ctx: Context<MigrateUser>) -> Result<()> {
+        let account = ctx.accounts.account.to_account_info();
+        grow_account(
+            &account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + User::LEN,
+        )?;
+        let mut user = User::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+        if user.pool != ctx.accounts.pool.key() {
+            return Err(ErrorCode::InvalidUserPool.into());
+        }
+        let old_version = migrate_user_account(&mut ctx.accounts.pool, &mut user)?;
+        let mut data = account.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        user.try_serialize(&mut writer)?;
+        let clock = clock::Clock::get()?;
+        emit!(EventMigrate {
+            pool: ctx.accounts.pool.key(),
+            account: account.key(),
+            payer: ctx.accounts.payer.key(),
+            old_version,
+            new_version: user.version,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Closes a pool account. Only able to be done when there are no users staked.
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        check_timelock(
+            &ctx.accounts.pool,
+            ctx.accounts.queued_action.as_deref(),
+            AdminAction::ClosePool,
+            ctx.accounts.authority.key(),
+        )?;
+        // pools that predate the index are not listed, nor is their staking mint always indexed
+        if ctx.accounts.pool_index.owner == &crate::ID {
+            let mut pool_index: Account<PoolIndex> =
+                Account::try_from(&ctx.accounts.pool_index.to_account_info())?;
+            pool_index.remove(&ctx.accounts.pool.key());
+            pool_index.exit(&crate::ID)?;
+        }
+        close_if_created::<PoolMetadata>(
+            &ctx.accounts.pool_metadata,
+            &ctx.accounts.refundee.to_account_info(),
+        )?;
+
+        let pool = &ctx.accounts.pool;
+        let reward_a_refund = ctx.accounts.reward_a_vault.amount;
+        let clock = clock::Clock::get()?;
+        emit!(EventClosePool {
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            staking_refund: ctx.accounts.staking_vault.amount,
+            reward_a_refund,
+            total_reward: pool.total_reward,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        let reward_duration = ctx.accounts.pool.reward_duration.to_be_bytes();
+        let signer_seeds = &[
+            reward_duration.as_ref(),
+            ctx.accounts.pool.staking_mint.as_ref(),
+            ctx.accounts.pool.reward_a_mint.as_ref(),
+            // ctx.accounts.pool.reward_b_mint.as_ref(),
+            ctx.accounts.pool.base_key.as_ref(),
+            &[ctx.accounts.pool.pool_bump],
+        ];
+
+        //instead of closing these vaults, we could technically just
+        //set_authority on them. it's not very ata clean, but it'd work
+        //if size of tx is an issue, thats an approach
+
+        //close staking vault
+        let ix = spl_token::instruction::transfer(
+            &spl_token::ID,
+            ctx.accounts.staking_vault.to_account_info().key,
+            ctx.accounts.staking_refundee.to_account_info().key,
+            &ctx.accounts.pool.key(),
+            &[&ctx.accounts.pool.key()],
+            ctx.accounts.staking_vault.amount,
+        )?;
+        solana_program::program::invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.staking_vault.to_account_info(),
                 ctx.accounts.staking_refundee.to_account_info(),
                 ctx.accounts.pool.to_account_info(),
             ],
@@ -682,7 +2433,7 @@ pub struct InitializePool<'info> {
         ],
         payer = authority,
         bump,
-        space = 8 + 494 // discriminator + content + buffer
+        space = 8 + Pool::LEN
     )]
     pool: Box<Account<'info, Pool>>,
     /// Staking mint
@@ -736,125 +2487,705 @@ pub struct InitializePool<'info> {
     // )]
     // reward_b_vault: Box<Account<'info, TokenAccount>>,
 
+    /// Program-wide config
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+    )]
+    global_config: Box<Account<'info, GlobalConfig>>,
+
+    /// Index of the pools sharing the staking mint, created with the first of them
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"pool_index",
+            staking_mint.key().as_ref(),
+        ],
+        bump,
+        payer = authority,
+        space = 8 + PoolIndex::LEN,
+    )]
+    pool_index: Box<Account<'info, PoolIndex>>,
+
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    /// Base
+    base: Signer<'info>,
+    /// System program
+    system_program: Program<'info, System>,
+    /// SPL Token program
+    token_program: Program<'info, Token>,
+    /// Rent
+    rent: Sysvar<'info, Rent>,
+
+}
+
+/// Accounts for [SetPoolMetadata](/dual_farming/instruction/struct.SetPoolMetadata.html) instruction
+#[derive(Accounts)]
+pub struct SetPoolMetadata<'info> {
+    /// Global accounts for the staking instance.
+    #[account(has_one = authority)]
+    pool: Box<Account<'info, Pool>>,
+    /// Metadata of the pool
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"metadata",
+            pool.key().as_ref(),
+        ],
+        bump,
+        payer = authority,
+        space = 8 + 500 // discriminator + content + buffer
+    )]
+    pool_metadata: Box<Account<'info, PoolMetadata>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// System program
+    system_program: Program<'info, System>,
+}
+
+/// Accounts for [CreateUser](/dual_farming/instruction/struct.CreateUser.html) instruction
+#[derive(Accounts)]
+pub struct CreateUser<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        constraint = !pool.is_paused(PAUSE_DEPOSIT) @ ErrorCode::DepositPaused,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// User
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            owner.key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+        space = 8 + User::LEN,
+    )]
+    user: Box<Account<'info, User>>,
+    /// Authority of user account
+    #[account(mut)]
+    owner: Signer<'info>,
+    /// Misc.
+    system_program: Program<'info, System>,
+}
+
+/// Accounts for [Checkpoint](/dual_farming/instruction/struct.Checkpoint.html) instruction
+#[derive(Accounts)]
+pub struct Checkpoint<'info> {
+    /// Global accounts for the staking instance.
+    #[account(mut)]
+    pool: Box<Account<'info, Pool>>,
+    /// Anyone
+    caller: Signer<'info>,
+}
+
+/// Accounts for [Pause](/dual_farming/instruction/struct.Pause.html) instruction
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = !pool.paused,
+        constraint = pool.reward_duration_end < clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap(),
+        constraint = pool.reward_duration_end > 0,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    authority: Signer<'info>,
+}
+
+/// Accounts for [Unpause](/dual_farming/instruction/struct.Unpause.html) instruction
+#[derive(Accounts)]
+pub struct Unpause<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = pool.paused,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    authority: Signer<'info>,
+}
+
+/// Accounts for [Deposit](/dual_farming/instruction/struct.Deposit.html) and [Withdraw](/dual_farming/instruction/struct.Withdraw.html) instructions.
+#[derive(Accounts)]
+pub struct UserReward<'info> {
+    pool: Box<Account<'info, Pool>>,
+    user: Box<Account<'info, User>>,
+    owner: Signer<'info>,
+}
+
+/// Accounts for [Deposit](/dual_farming/instruction/struct.Deposit.html) and [Withdraw](/dual_farming/instruction/struct.Withdraw.html) instructions.
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    /// Global accounts for the deposit/withdraw instance.
+    #[account(
+        mut,
+        has_one = staking_vault,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Staking vault PDA.
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    /// User.
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.nonce,
+    )]
+    user: Box<Account<'info, User>>,
+    /// Authority of user
+    owner: Signer<'info>,
+    /// User staking ATA
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.staking_mint @ ErrorCode::InvalidStakingMint,
+        constraint = stake_from_account.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+}
+
+/// Accounts for [Withdraw](/dual_farming/instruction/struct.Withdraw.html) instruction
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    /// Global accounts for the deposit/withdraw instance.
+    #[account(
+        mut,
+        has_one = staking_vault,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Staking vault PDA.
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    /// User.
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.nonce,
+    )]
+    user: Box<Account<'info, User>>,
+    /// Authority of user
+    owner: Signer<'info>,
+    /// User staking ATA
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.staking_mint @ ErrorCode::InvalidStakingMint,
+        constraint = stake_from_account.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+    /// Reward A Vault PDA, receives the early exit penalty taken out of the principal.
+    /// Last so that clients built before it was added can leave it out.
+    #[account(
+        mut,
+        address = pool.reward_a_vault,
+    )]
+    reward_a_vault: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+/// Accounts for [DepositWithInit](/dual_farming/instruction/struct.DepositWithInit.html) instruction
+#[derive(Accounts)]
+pub struct DepositWithInit<'info> {
+    /// Global accounts for the deposit/withdraw instance.
+    #[account(
+        mut,
+        has_one = staking_vault,
+        constraint = !pool.is_paused(PAUSE_DEPOSIT) @ ErrorCode::DepositPaused,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Staking vault PDA.
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: User, created on the first deposit and migrated when it predates the current
+    /// layout, see `load_or_init_user`.
+    #[account(
+        mut,
+        seeds = [
+            owner.key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+    )]
+    user: UncheckedAccount<'info>,
+    /// Authority of user
+    #[account(mut)]
+    owner: Signer<'info>,
+    /// User staking ATA
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.staking_mint @ ErrorCode::InvalidStakingMint,
+        constraint = stake_from_account.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+    /// System program
+    system_program: Program<'info, System>,
+}
+
+/// Accounts for [DepositFor](/dual_farming/instruction/struct.DepositFor.html) instruction
+#[derive(Accounts)]
+pub struct DepositFor<'info> {
+    /// Global accounts for the deposit/withdraw instance.
+    #[account(
+        mut,
+        has_one = staking_vault,
+        constraint = !pool.is_paused(PAUSE_DEPOSIT) @ ErrorCode::DepositPaused,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Staking vault PDA.
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Beneficiary user, created on the first deposit and migrated when it predates the
+    /// current layout, see `load_or_init_user`.
+    #[account(
+        mut,
+        seeds = [
+            beneficiary.key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+    )]
+    user: UncheckedAccount<'info>,
+    /// Owner of the beneficiary user, need not sign as the deposit leaves their stake windows alone
+    /// CHECK: any wallet, only used to derive the beneficiary user
+    beneficiary: UncheckedAccount<'info>,
+    /// Payer of the staked tokens and of the user account rent
+    #[account(mut)]
+    payer: Signer<'info>,
+    /// Payer's own user in the pool, which may not exist
+    /// CHECK: only read once created by the program, see `is_frozen_user`.
+    #[account(
+        seeds = [
+            payer.key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+    )]
+    payer_user: UncheckedAccount<'info>,
+    /// Payer staking ATA
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.staking_mint @ ErrorCode::InvalidStakingMint,
+        constraint = stake_from_account.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+    /// System program
+    system_program: Program<'info, System>,
+}
+
+/// Accounts for [AuthorizeFunder](/dual_farming/instruction/struct.AuthorizeFunder.html)
+/// and [DeauthorizeFunder](/dual_farming/instruction/struct.DeauthorizeFunder.html) instructions.
+#[derive(Accounts)]
+pub struct FunderChange<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
+}
+
+/// Accounts for [AppendEmissionSegment](/dual_farming/instruction/struct.AppendEmissionSegment.html) instruction.
+#[derive(Accounts)]
+pub struct UpdateEmission<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
+}
+
+/// Accounts for [Fund](/dual_farming/instruction/struct.Fund.html) instruction.
+#[derive(Accounts)]
+pub struct Fund<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = staking_vault,
+        has_one = reward_a_vault,
+        // has_one = reward_b_vault,
+        constraint = !pool.is_paused(PAUSE_FUND) @ ErrorCode::FundPaused,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Staking vault PDA
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    /// Reward A Vault PDA
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    /// Reward B Vault PDA
+    // #[account(mut)]
+    // reward_b_vault: Box<Account<'info, TokenAccount>>,
+    /// Funder
+    #[account(
+        //require signed funder auth - otherwise constant micro fund could hold funds hostage
+        constraint = funder.key() == pool.authority || pool.funders.iter().any(|x| *x == funder.key()),
+    )]
+    funder: Signer<'info>,
+    /// Funder reward A ATA
+    #[account(
+        mut,
+        constraint = from_a.mint == pool.reward_a_mint @ ErrorCode::InvalidRewardMint,
+        constraint = from_a.owner == funder.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    from_a: Box<Account<'info, TokenAccount>>,
+    /// Funder reward B ATA
+    // #[account(mut)]
+    // from_b: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+}
+
+/// Accounts for [StartEpoch](/dual_farming/instruction/struct.StartEpoch.html) instruction
+#[derive(Accounts)]
+#[instruction(epoch_number: u64)]
+pub struct StartEpoch<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = reward_a_vault,
+        constraint = !pool.is_paused(PAUSE_FUND) @ ErrorCode::FundPaused,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Epoch
+    #[account(
+        init,
+        seeds = [
+            b"epoch",
+            pool.key().as_ref(),
+            epoch_number.to_le_bytes().as_ref(),
+        ],
+        bump,
+        payer = funder,
+        space = 8 + Epoch::LEN,
+    )]
+    epoch: Box<Account<'info, Epoch>>,
+    /// Reward A Vault PDA
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    /// Funder
+    #[account(
+        mut,
+        constraint = funder.key() == pool.authority || pool.funders.iter().any(|x| *x == funder.key()),
+    )]
+    funder: Signer<'info>,
+    /// Funder reward A ATA
+    #[account(
+        mut,
+        constraint = from_a.mint == pool.reward_a_mint @ ErrorCode::InvalidRewardMint,
+        constraint = from_a.owner == funder.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    from_a: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+    /// System program
+    system_program: Program<'info, System>,
+}
+
+/// Accounts for [FinalizeEpoch](/dual_farming/instruction/struct.FinalizeEpoch.html) instruction
+#[derive(Accounts)]
+pub struct FinalizeEpoch<'info> {
+    /// Global accounts for the staking instance.
+    #[account(mut)]
+    pool: Box<Account<'info, Pool>>,
+    /// Current epoch of the pool
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [
+            b"epoch",
+            pool.key().as_ref(),
+            epoch.epoch.to_le_bytes().as_ref(),
+        ],
+        bump = epoch.bump,
+        constraint = epoch.epoch == pool.epoch_count @ ErrorCode::InvalidEpoch,
+        constraint = !epoch.finalized @ ErrorCode::EpochAlreadyFinalized,
+    )]
+    epoch: Box<Account<'info, Epoch>>,
+    /// Anyone
+    caller: Signer<'info>,
+}
+
+/// Accounts for [SweepEpoch](/dual_farming/instruction/struct.SweepEpoch.html) instruction
+#[derive(Accounts)]
+pub struct SweepEpoch<'info> {
+    /// Global accounts for the staking instance.
+    #[account(mut)]
+    pool: Box<Account<'info, Pool>>,
+    /// Finalized epoch being swept
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [
+            b"epoch",
+            pool.key().as_ref(),
+            epoch.epoch.to_le_bytes().as_ref(),
+        ],
+        bump = epoch.bump,
+        constraint = epoch.finalized @ ErrorCode::EpochNotFinalized,
+    )]
+    epoch: Box<Account<'info, Epoch>>,
+    /// Anyone
+    caller: Signer<'info>,
+}
+
+/// Accounts for [SetPauseFlags](/dual_farming/instruction/struct.SetPauseFlags.html) instruction
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    authority: Signer<'info>,
+}
+
+/// Accounts for [FreezeUser](/dual_farming/instruction/struct.FreezeUser.html)
+/// and [UnfreezeUser](/dual_farming/instruction/struct.UnfreezeUser.html) instructions
+#[derive(Accounts)]
+pub struct FreezeUser<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// User account to freeze or unfreeze
+    #[account(
+        mut,
+        has_one = pool,
+    )]
+    user: Box<Account<'info, User>>,
+    /// Authority of the pool
+    authority: Signer<'info>,
+}
+
+/// Accounts for [SetMinStakeSeconds](/dual_farming/instruction/struct.SetMinStakeSeconds.html) instruction
+#[derive(Accounts)]
+pub struct SetMinStakeSeconds<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
+}
+
+/// Accounts for [SetTimelockSeconds](/dual_farming/instruction/struct.SetTimelockSeconds.html) instruction
+#[derive(Accounts)]
+pub struct SetTimelockSeconds<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
+}
+
+/// Accounts for [SetOutflowLimit](/dual_farming/instruction/struct.SetOutflowLimit.html) instruction
+#[derive(Accounts)]
+pub struct SetOutflowLimit<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pool: Box<Account<'info, Pool>>,
     /// Authority of the pool
     #[account(mut)]
     authority: Signer<'info>,
-
-    /// Base
-    base: Signer<'info>,
-    /// System program
-    system_program: Program<'info, System>,
-    /// SPL Token program
-    token_program: Program<'info, Token>,
-    /// Rent
-    rent: Sysvar<'info, Rent>,
-
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
-/// Accounts for [CreateUser](/dual_farming/instruction/struct.CreateUser.html) instruction
+/// Accounts for [SetEarlyExitPenalty](/dual_farming/instruction/struct.SetEarlyExitPenalty.html) instruction
 #[derive(Accounts)]
-pub struct CreateUser<'info> {
+pub struct SetEarlyExitPenalty<'info> {
     /// Global accounts for the staking instance.
     #[account(
         mut,
-        constraint = !pool.paused,
+        has_one = authority,
     )]
     pool: Box<Account<'info, Pool>>,
-    /// User
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
     #[account(
-        init,
-        payer = owner,
-        seeds = [
-            owner.key.as_ref(),
-            pool.to_account_info().key.as_ref()
-        ],
-        bump,
-        space = 200, //8 + 32 + 32 + 16 + 16 + 8 + 8 + 8 + 1 + buffer
+        mut,
+        has_one = pool,
+        close = authority,
     )]
-    user: Box<Account<'info, User>>,
-    /// Authority of user account
-    #[account(mut)]
-    owner: Signer<'info>,
-    /// Misc.
-    system_program: Program<'info, System>,
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
-/// Accounts for [Pause](/dual_farming/instruction/struct.Pause.html) instruction
+/// Accounts for [SetUtilizationCurve](/dual_farming/instruction/struct.SetUtilizationCurve.html) instruction
 #[derive(Accounts)]
-pub struct Pause<'info> {
+pub struct SetUtilizationCurve<'info> {
     /// Global accounts for the staking instance.
     #[account(
         mut,
         has_one = authority,
-        constraint = !pool.paused,
-        constraint = pool.reward_duration_end < clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap(),
-        constraint = pool.reward_duration_end > 0,
     )]
     pool: Box<Account<'info, Pool>>,
     /// Authority of the pool
+    #[account(mut)]
     authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
-/// Accounts for [Unpause](/dual_farming/instruction/struct.Unpause.html) instruction
+/// Accounts for [SetPointsRate](/dual_farming/instruction/struct.SetPointsRate.html) instruction
 #[derive(Accounts)]
-pub struct Unpause<'info> {
+pub struct SetPointsRate<'info> {
     /// Global accounts for the staking instance.
     #[account(
         mut,
         has_one = authority,
-        constraint = pool.paused,
     )]
     pool: Box<Account<'info, Pool>>,
     /// Authority of the pool
+    #[account(mut)]
     authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
-/// Accounts for [Deposit](/dual_farming/instruction/struct.Deposit.html) and [Withdraw](/dual_farming/instruction/struct.Withdraw.html) instructions.
+/// Accounts for [QueueAction](/dual_farming/instruction/struct.QueueAction.html) instruction
 #[derive(Accounts)]
-pub struct UserReward<'info> {
+#[instruction(action_id: u64)]
+pub struct QueueAction<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        has_one = authority,
+    )]
     pool: Box<Account<'info, Pool>>,
-    user: Box<Account<'info, User>>,
-    owner: Signer<'info>,
+    /// Queued action
+    #[account(
+        init,
+        seeds = [
+            b"queued_action",
+            pool.key().as_ref(),
+            action_id.to_le_bytes().as_ref(),
+        ],
+        bump,
+        payer = authority,
+        space = 8 + QueuedAction::LEN,
+    )]
+    queued_action: Box<Account<'info, QueuedAction>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// System program
+    system_program: Program<'info, System>,
 }
 
-/// Accounts for [Deposit](/dual_farming/instruction/struct.Deposit.html) and [Withdraw](/dual_farming/instruction/struct.Withdraw.html) instructions.
+/// Accounts for [CancelAction](/dual_farming/instruction/struct.CancelAction.html) instruction
 #[derive(Accounts)]
-pub struct Deposit<'info> {
-    /// Global accounts for the deposit/withdraw instance.
+pub struct CancelAction<'info> {
+    /// Global accounts for the staking instance.
     #[account(
-        mut,
-        has_one = staking_vault,
+        has_one = authority,
     )]
     pool: Box<Account<'info, Pool>>,
-    /// Staking vault PDA.
-    #[account(mut)]
-    staking_vault: Box<Account<'info, TokenAccount>>,
-
-    /// User.
+    /// Queued action to cancel
     #[account(
         mut,
-        has_one = owner,
         has_one = pool,
-        seeds = [
-            owner.key.as_ref(),
-            pool.to_account_info().key.as_ref()
-        ],
-        bump = user.nonce,
+        close = authority,
     )]
-    user: Box<Account<'info, User>>,
-    /// Authority of user
-    owner: Signer<'info>,
-    /// User staking ATA
+    queued_action: Box<Account<'info, QueuedAction>>,
+    /// Authority of the pool
     #[account(mut)]
-    stake_from_account: Box<Account<'info, TokenAccount>>,
-    /// Misc.
-    token_program: Program<'info, Token>,
+    authority: Signer<'info>,
 }
 
-/// Accounts for [AuthorizeFunder](/dual_farming/instruction/struct.AuthorizeFunder.html)
-/// and [DeauthorizeFunder](/dual_farming/instruction/struct.DeauthorizeFunder.html) instructions.
+/// Accounts for [SetRewardDuration](/dual_farming/instruction/struct.SetRewardDuration.html) instruction
 #[derive(Accounts)]
-pub struct FunderChange<'info> {
+pub struct SetRewardDuration<'info> {
     /// Global accounts for the staking instance.
     #[account(
         mut,
@@ -862,66 +3193,88 @@ pub struct FunderChange<'info> {
     )]
     pool: Box<Account<'info, Pool>>,
     /// Authority of the pool
+    #[account(mut)]
     authority: Signer<'info>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
-/// Accounts for [Fund](/dual_farming/instruction/struct.Fund.html) instruction.
+/// Accounts for [WithdrawExtraToken](/dual_farming/instruction/struct.WithdrawExtraToken.html) instruction
 #[derive(Accounts)]
-pub struct Fund<'info> {
+pub struct WithdrawExtraToken<'info> {
     /// Global accounts for the staking instance.
     #[account(
         mut,
         has_one = staking_vault,
-        has_one = reward_a_vault,
-        // has_one = reward_b_vault,
-        constraint = !pool.paused,
+        has_one = authority,
+        constraint = pool.reward_duration_end < sysvar::clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap(),
     )]
     pool: Box<Account<'info, Pool>>,
     /// Staking vault PDA
     #[account(mut)]
     staking_vault: Box<Account<'info, TokenAccount>>,
-    /// Reward A Vault PDA
-    #[account(mut)]
-    reward_a_vault: Box<Account<'info, TokenAccount>>,
-    /// Reward B Vault PDA
-    // #[account(mut)]
-    // reward_b_vault: Box<Account<'info, TokenAccount>>,
-    /// Funder
+    /// Token account to receive mistakenly deposited token
     #[account(
-        //require signed funder auth - otherwise constant micro fund could hold funds hostage
-        constraint = funder.key() == pool.authority || pool.funders.iter().any(|x| *x == funder.key()),
+        mut,
+        constraint = withdraw_to_account.mint == pool.staking_mint @ ErrorCode::InvalidStakingMint,
+        constraint = withdraw_to_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
     )]
-    funder: Signer<'info>,
-    /// Funder reward A ATA
+    withdraw_to_account: Box<Account<'info, TokenAccount>>,
+    /// Authority of the staking instance
     #[account(mut)]
-    from_a: Box<Account<'info, TokenAccount>>,
-    /// Funder reward B ATA
-    // #[account(mut)]
-    // from_b: Box<Account<'info, TokenAccount>>,
+    authority: Signer<'info>,
     /// Misc.
     token_program: Program<'info, Token>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
-/// Accounts for [WithdrawExtraToken](/dual_farming/instruction/struct.WithdrawExtraToken.html) instruction
+/// Accounts for [WithdrawUnallocatedRewards](/dual_farming/instruction/struct.WithdrawUnallocatedRewards.html) instruction
 #[derive(Accounts)]
-pub struct WithdrawExtraToken<'info> {
+pub struct WithdrawUnallocatedRewards<'info> {
     /// Global accounts for the staking instance.
     #[account(
-        has_one = staking_vault,
+        mut,
+        has_one = reward_a_vault,
         has_one = authority,
+        constraint = pool.reward_duration_end > 0,
         constraint = pool.reward_duration_end < sysvar::clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap(),
+        // total_pending misses the rewards owed to legacy users until they are migrated
+        constraint = pool.legacy_user_count == 0 @ ErrorCode::UsersNotMigrated,
     )]
     pool: Box<Account<'info, Pool>>,
-    /// Staking vault PDA
-    #[account(mut)]
-    staking_vault: Box<Account<'info, TokenAccount>>,
-    /// Token account to receive mistakenly deposited token
+    /// Reward A Vault PDA
     #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    /// Token account to receive the unallocated rewards
+    #[account(
+        mut,
+        constraint = withdraw_to_account.mint == pool.reward_a_mint @ ErrorCode::InvalidRewardMint,
+        constraint = withdraw_to_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
     withdraw_to_account: Box<Account<'info, TokenAccount>>,
     /// Authority of the staking instance
+    #[account(mut)]
     authority: Signer<'info>,
     /// Misc.
     token_program: Program<'info, Token>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
 }
 
 /// Accounts for [Claim](/dual_farming/instruction/struct.Claim.html) instruction.
@@ -960,7 +3313,11 @@ pub struct ClaimReward<'info> {
     /// Authority of user
     owner: Signer<'info>,
     /// User's Reward A ATA
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = reward_a_account.mint == pool.reward_a_mint @ ErrorCode::InvalidRewardMint,
+        constraint = reward_a_account.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
     reward_a_account: Box<Account<'info, TokenAccount>>,
     /// User's Reward B ATA
     // #[account(mut)]
@@ -968,47 +3325,225 @@ pub struct ClaimReward<'info> {
     // Misc.
     token_program: Program<'info, Token>,
 }
+
+/// Accounts for [ClaimEpoch](/dual_farming/instruction/struct.ClaimEpoch.html) instruction.
+#[derive(Accounts)]
+pub struct ClaimEpoch<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = reward_a_vault,
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Finalized epoch being claimed
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [
+            b"epoch",
+            pool.key().as_ref(),
+            epoch.epoch.to_le_bytes().as_ref(),
+        ],
+        bump = epoch.bump,
+        constraint = epoch.finalized @ ErrorCode::EpochNotFinalized,
+    )]
+    epoch: Box<Account<'info, Epoch>>,
+    /// Reward A Vault PDA
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    /// User.
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.nonce,
+    )]
+    user: Box<Account<'info, User>>,
+    /// Authority of user
+    owner: Signer<'info>,
+    /// User's Reward A ATA
+    #[account(
+        mut,
+        constraint = reward_a_account.mint == pool.reward_a_mint @ ErrorCode::InvalidRewardMint,
+        constraint = reward_a_account.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    reward_a_account: Box<Account<'info, TokenAccount>>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+}
+
+/// Accounts for [ClaimMany](/dual_farming/instruction/struct.ClaimMany.html) instruction.
+/// Pools are passed through `remaining_accounts`, see `CLAIM_MANY_GROUP_LEN`.
+#[derive(Accounts)]
+pub struct ClaimMany<'info> {
+    /// Authority of the users
+    owner: Signer<'info>,
+    /// SPL Token program
+    token_program: Program<'info, Token>,
+}
+
 /// Accounts for [CloseUser](/dual_farming/instruction/struct.CloseUser.html) instruction
 #[derive(Accounts)]
 pub struct CloseUser<'info> {
     #[account(mut)]
-    pool: Box<Account<'info, Pool>>,
+    pool: Box<Account<'info, Pool>>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.nonce,
+        constraint = user.balance_staked == 0,
+        constraint = user.reward_a_per_token_pending == 0,
+        // constraint = user.reward_b_per_token_pending == 0,
+        // a frozen account is kept, recreating it would drop the freeze
+        constraint = !user.frozen @ ErrorCode::UserFrozen,
+    )]
+    user: Account<'info, User>,
+    // To receive lamports when close the user account
+    #[account(mut)]
+    owner: Signer<'info>,
+}
+
+/// Accounts for [ForceCloseUser](/dual_farming/instruction/struct.ForceCloseUser.html) instruction
+#[derive(Accounts)]
+pub struct ForceCloseUser<'info> {
+    /// Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = staking_vault,
+        has_one = reward_a_vault,
+        constraint = pool.reward_duration_end > 0,
+        constraint = pool.reward_duration_end.checked_add(FORCE_CLOSE_GRACE_PERIOD).unwrap() < clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap(),
+    )]
+    pool: Box<Account<'info, Pool>>,
+    /// Staking vault PDA.
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    /// Reward A Vault PDA
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    /// User.
     #[account(
         mut,
         close = owner,
         has_one = owner,
         has_one = pool,
         seeds = [
-            owner.to_account_info().key.as_ref(),
+            owner.key.as_ref(),
             pool.to_account_info().key.as_ref()
         ],
         bump = user.nonce,
-        constraint = user.balance_staked == 0,
-        constraint = user.reward_a_per_token_pending == 0,
-        // constraint = user.reward_b_per_token_pending == 0,
     )]
-    user: Account<'info, User>,
-    // To receive lamports when close the user account
+    user: Box<Account<'info, User>>,
+    /// Owner of the user account, receives the rent
+    /// CHECK: validated by `has_one = owner` on the user account
     #[account(mut)]
-    owner: Signer<'info>,
+    owner: UncheckedAccount<'info>,
+    /// Owner's staking ATA
+    #[account(
+        mut,
+        address = get_associated_token_address(owner.key, &pool.staking_mint),
+    )]
+    owner_staking_account: Box<Account<'info, TokenAccount>>,
+    /// Owner's reward A ATA
+    #[account(
+        mut,
+        address = get_associated_token_address(owner.key, &pool.reward_a_mint),
+    )]
+    owner_reward_a_account: Box<Account<'info, TokenAccount>>,
+    /// Authority of the pool
+    #[account(mut)]
+    authority: Signer<'info>,
+    /// Misc.
+    token_program: Program<'info, Token>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
+}
+
+/// Accounts for [MigratePool](/dual_farming/instruction/struct.MigratePool.html) instruction
+/// The account is unchecked as older layouts are too short to deserialize before growing,
+/// which rules out the `realloc` constraint. The discriminator is checked on deserialization.
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// Pool account to migrate
+    /// CHECK: owned by the program, discriminator checked by the instruction
+    #[account(mut, owner = crate::ID)]
+    account: UncheckedAccount<'info>,
+    /// Staking mint of the pool, checked by the instruction
+    staking_mint: Box<Account<'info, Mint>>,
+    /// Reward A mint of the pool, checked by the instruction
+    reward_a_mint: Box<Account<'info, Mint>>,
+    /// Pays the rent of the extra space
+    #[account(mut)]
+    payer: Signer<'info>,
+    /// System program
+    system_program: Program<'info, System>,
 }
 
 /// Accounts for [MigrateFarmingRate](/dual_farming/instruction/struct.MigrateFarmingRate.html) instruction
 #[derive(Accounts)]
 pub struct MigrateFarmingRate<'info> {
+    /// Pool account
+    #[account(mut)]
+    pool: Box<Account<'info, Pool>>,
+}
+
+/// Accounts for [MigrateUser](/dual_farming/instruction/struct.MigrateUser.html) instruction
+/// The user account is unchecked for the same reason as in [MigrateAccount].
+#[derive(Accounts)]
+pub struct MigrateUser<'info> {
+    /// Pool of the user, counts the pending rewards of legacy users. Must be migrated first.
     #[account(mut)]
     pool: Box<Account<'info, Pool>>,
+    /// User account to migrate
+    /// CHECK: owned by the program, discriminator and pool checked by the instruction
+    #[account(mut, owner = crate::ID)]
+    account: UncheckedAccount<'info>,
+    /// Pays the rent of the extra space
+    #[account(mut)]
+    payer: Signer<'info>,
+    /// System program
+    system_program: Program<'info, System>,
 }
 
 /// Accounts for [ClosePool](/dual_farming/instruction/struct.ClosePool.html) instruction
 #[derive(Accounts)]
 pub struct ClosePool<'info> {
-    #[account(mut)]
-    /// CHECK: refundee
+    /// Receives the rent of the closed accounts
+    /// CHECK: bound to the pool authority
+    #[account(
+        mut,
+        constraint = refundee.key() == authority.key() @ ErrorCode::InvalidRefundee,
+    )]
     refundee: UncheckedAccount<'info>,
-    #[account(mut)]
+    /// Authority staking token account, receives the staking vault dust
+    #[account(
+        mut,
+        constraint = staking_refundee.mint == pool.staking_mint @ ErrorCode::InvalidStakingMint,
+        constraint = staking_refundee.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
     staking_refundee: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
+    /// Authority reward A token account, receives the remaining rewards
+    #[account(
+        mut,
+        constraint = reward_a_refundee.mint == pool.reward_a_mint @ ErrorCode::InvalidRewardMint,
+        constraint = reward_a_refundee.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
     reward_a_refundee: Box<Account<'info, TokenAccount>>,
     // #[account(mut)]
     // reward_b_refundee: Box<Account<'info, TokenAccount>>,
@@ -1025,6 +3560,29 @@ pub struct ClosePool<'info> {
         constraint = pool.user_stake_count == 0,
     )]
     pool: Account<'info, Pool>,
+    /// Index of the pools sharing the staking mint, the pool is removed from it if created
+    /// CHECK: address checked by the seeds, deserialized by the instruction if created
+    #[account(
+        mut,
+        seeds = [
+            b"pool_index",
+            pool.staking_mint.as_ref(),
+        ],
+        bump,
+    )]
+    pool_index: UncheckedAccount<'info>,
+    /// Metadata of the pool, closed along with it when the pool has some
+    /// CHECK: address checked by the seeds, closed by the instruction if created
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            pool.key().as_ref(),
+        ],
+        bump,
+    )]
+    pool_metadata: UncheckedAccount<'info>,
+    #[account(mut)]
     authority: Signer<'info>,
     #[account(mut,
         constraint = staking_vault.amount == 0, // Admin need to withdraw out mistakenly deposited token firstly
@@ -1035,11 +3593,63 @@ pub struct ClosePool<'info> {
     // #[account(mut)]
     // reward_b_vault: Box<Account<'info, TokenAccount>>,
     token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    /// Queued action being executed, required while the pool has a timelock
+    #[account(
+        mut,
+        has_one = pool,
+        close = authority,
+    )]
+    queued_action: Option<Box<Account<'info, QueuedAction>>>,
+}
+
+/// Accounts for [InitializeGlobalConfig](/dual_farming/instruction/struct.InitializeGlobalConfig.html) instruction
+#[derive(Accounts)]
+pub struct InitializeGlobalConfig<'info> {
+    /// Program-wide config
+    #[account(
+        init,
+        seeds = [b"global_config"],
+        bump,
+        payer = admin,
+        space = 8 + 700 // discriminator + content + buffer
+    )]
+    global_config: Box<Account<'info, GlobalConfig>>,
+    /// Program admin, must be the program upgrade authority
+    #[account(mut)]
+    admin: Signer<'info>,
+    /// The farming program
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    program: Program<'info, crate::program::Farming>,
+    /// Program data of the farming program, holds its upgrade authority
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::NotUpgradeAuthority,
+    )]
+    program_data: Account<'info, ProgramData>,
+    /// System program
+    system_program: Program<'info, System>,
+}
+
+/// Accounts for [UpdateGlobalConfig](/dual_farming/instruction/struct.UpdateGlobalConfig.html),
+/// [AllowStakingMint](/dual_farming/instruction/struct.AllowStakingMint.html)
+/// and [DisallowStakingMint](/dual_farming/instruction/struct.DisallowStakingMint.html) instructions.
+#[derive(Accounts)]
+pub struct GlobalConfigChange<'info> {
+    /// Program-wide config
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = admin,
+    )]
+    global_config: Box<Account<'info, GlobalConfig>>,
+    /// Program admin
+    admin: Signer<'info>,
 }
 
 /// Pool account wrapper
 #[account]
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Pool {
     /// Privileged account.
     pub authority: Pubkey, // 32
@@ -1087,10 +3697,254 @@ pub struct Pool {
     pub pool_bump: u8, // 1
     /// Total staked amount
     pub total_staked: u64,
-    pub total_reward:u64
+    pub total_reward:u64,
+    /// Emission schedule, a segment with zero start_time is an unused slot
+    pub emission_segments: [EmissionSegment; MAX_EMISSION_SEGMENTS], // 32 * 8 = 256
+    /// The timestamp at which the current reward period starts.
+    pub reward_start_time: u64, // 8
+    /// Length of reward periods, reward_duration stays fixed as part of the pool seeds.
+    pub current_period_duration: u64, // 8
+    /// Per-operation pause flags, see PAUSE_*
+    pub pause_flags: u8, // 1
+    /// Rewards credited to users and not claimed yet.
+    /// Complete once `legacy_user_count` is zero, see `migrate_user`.
+    pub total_pending: u64, // 8
+    /// Layout version, see POOL_VERSION
+    pub version: u8, // 1
+    /// Time up to which reward_a_per_token_stored has been advanced.
+    /// Kept apart from last_update_time, which drives user tiers.
+    pub checkpoint_time: u64, // 8
+    /// Seconds a deposit must stay staked before it earns rewards, 0 to disable.
+    pub min_stake_seconds: u64, // 8
+    /// Delay between queueing an admin action and executing it, 0 to disable.
+    pub timelock_seconds: u64, // 8
+    /// Share of the staked tokens, in basis points, that may leave the pool per outflow window. 0 to disable.
+    pub outflow_limit_bps: u16, // 2
+    /// Length of the outflow window
+    pub outflow_window_seconds: u64, // 8
+    /// Timestamp at which the current outflow window started
+    pub outflow_window_start: u64, // 8
+    /// Tokens that left the pool in the current outflow window
+    pub outflow_window_amount: u64, // 8
+    /// Highest total_staked seen in the current outflow window, the limit applies to it
+    pub outflow_window_base: u64, // 8
+    /// Share of the principal, or of the pending rewards when the mints differ, in basis points,
+    /// forfeited by withdrawals of stake within `early_exit_window_seconds` of its deposit. 0 to disable.
+    pub early_exit_penalty_bps: u16, // 2
+    /// Holding window after a deposit during which withdrawals pay the early exit penalty
+    pub early_exit_window_seconds: u64, // 8
+    /// Emission rate by total staked, replaces the emission schedule and the annual budget once set
+    pub utilization_curve: UtilizationCurve, // 24 * 4 + 1 = 97
+    /// Decimals of the staking mint, read from the mint by `migrate_pool` on older pools.
+    pub staking_decimals: u8, // 1
+    /// Decimals of the reward A mint, read from the mint by `migrate_pool` on older pools.
+    pub reward_decimals: u8, // 1
+    /// Points earned per staked token, in UI units, per second, scaled by PRECISION. 0 to disable.
+    pub points_per_token_second: u128, // 16
+    /// Points per staked base unit accumulated up to points_checkpoint_time, scaled by PRECISION.
+    pub points_per_token_stored: u128, // 16
+    /// Time up to which points_per_token_stored has been advanced.
+    pub points_checkpoint_time: u64, // 8
+    /// Whether users earn points only, with no token rewards credited.
+    pub points_only: bool, // 1
+    /// Number of epochs started, the current epoch is numbered `epoch_count`. Epochs are numbered from 1.
+    pub epoch_count: u64, // 8
+    /// Windows of the most recent epochs, each new epoch takes the slot of the oldest one
+    pub epochs: [EpochWindow; EPOCH_HISTORY], // 24 * 4 = 96
+    /// Stake-seconds of the whole pool in the current epoch up to epoch_checkpoint_time
+    pub epoch_stake_seconds: u128, // 16
+    /// Time up to which epoch_stake_seconds has been advanced
+    pub epoch_checkpoint_time: u64, // 8
+    /// Whether the current epoch has been finalized, the next epoch cannot start before
+    pub epoch_finalized: bool, // 1
+    /// Epoch budgets held in the reward A vault and not claimed yet
+    pub epoch_reserved: u64, // 8
+    /// Users created before total_pending was tracked whose pending rewards are not counted in it yet
+    pub legacy_user_count: u32, // 4
+    /// Timestamp until which withdrawals are paused after the outflow limit has been reached
+    pub outflow_paused_until: u64, // 8
 }
 
 impl Pool {
+    /// Serialized size of the current layout, without discriminator
+    pub const LEN: usize = 1013;
+
+    /// fills in the fields added by each version up to POOL_VERSION, given the decimals of the pool mints
+    pub fn migrate(&mut self, staking_decimals: u8, reward_decimals: u8) {
+        while self.version < POOL_VERSION {
+            match self.version {
+                0 => {
+                    if self.reward_a_rate_u128 == 0 && self._reward_a_rate != 0 {
+                        self.reward_a_rate_u128 = self._reward_a_rate.into();
+                    }
+                    if self.current_period_duration == 0 {
+                        self.current_period_duration = self.reward_duration;
+                    }
+                    // every user predates total_pending, migrate_user counts them in one by one
+                    self.legacy_user_count = self.user_stake_count;
+                }
+                1 => {
+                    self.checkpoint_time = self.last_update_time;
+                }
+                // the fields added since are zero on a grown account, which is what they are
+                // on a new pool until the authority opts in:
+                // min_stake_seconds, 0 disables the maturity lock
+                2 => {}
+                // timelock_seconds, 0 disables the timelock
+                3 => {}
+                // outflow limit, 0 disables it and the window starts on the first outflow
+                4 => {}
+                // early exit penalty, 0 disables it
+                5 => {}
+                // utilization curve, no points means the curve is unused
+                6 => {}
+                // mint decimals, from then on the emission parameters are in UI units
+                7 => {
+                    self.staking_decimals = staking_decimals;
+                    self.reward_decimals = reward_decimals;
+                }
+                // points, a zero rate accrues nothing
+                8 => {}
+                // epochs, none started
+                9 => {}
+                // legacy_user_count, every user of a versioned pool is counted in total_pending
+                10 => {}
+                // outflow_paused_until, not paused
+                11 => {}
+                _ => {}
+            }
+            self.version = self.version.saturating_add(1);
+        }
+    }
+
+    /// outflow window at `current_time` as (start, amount, base), a fresh one once the current window has ended
+    fn outflow_window_at(&self, current_time: u64) -> Option<(u64, u64, u64)> {
+        let window_end = self
+            .outflow_window_start
+            .checked_add(self.outflow_window_seconds)?;
+        let (start, amount, base) = if self.outflow_window_start == 0 || current_time >= window_end {
+            (current_time, 0, 0)
+        } else {
+            (
+                self.outflow_window_start,
+                self.outflow_window_amount,
+                self.outflow_window_base,
+            )
+        };
+        Some((start, amount, std::cmp::max(base, self.total_staked)))
+    }
+
+    /// tokens allowed to leave the pool in an outflow window with `base` staked
+    fn outflow_limit(&self, base: u64) -> Option<u128> {
+        u128::from(base)
+            .checked_mul(self.outflow_limit_bps.into())?
+            .checked_div(MAX_FEE_BPS.into())
+    }
+
+    /// advances reward_a_per_token_stored to `current_time`.
+    /// last_update_time, which drives user tiers, is left untouched.
+    pub fn checkpoint(&mut self, current_time: u64) -> Option<()> {
+        let from = std::cmp::max(self.checkpoint_time, self.reward_start_time);
+        let mut to = current_time;
+        if self.reward_duration_end > 0 {
+            to = std::cmp::min(to, self.reward_duration_end);
+        }
+        if self.total_staked > 0 && from < to {
+            let emission = self.emission_at_stake(from, to, self.total_staked)?;
+            self.reward_a_per_token_stored = self
+                .reward_a_per_token_stored
+                .checked_add(emission.checked_div(self.total_staked.into())?)?;
+        }
+        self.checkpoint_time = std::cmp::max(self.checkpoint_time, current_time);
+        Some(())
+    }
+
+    /// records `amount` leaving the pool at `current_time` against the outflow limit.
+    /// Returns false, recording nothing, when the outflow would go over the limit.
+    pub fn record_outflow(&mut self, amount: u64, current_time: u64) -> Option<bool> {
+        if self.outflow_limit_bps == 0 {
+            return Some(true);
+        }
+        let (start, window_amount, base) = self.outflow_window_at(current_time)?;
+        self.outflow_window_start = start;
+        self.outflow_window_amount = window_amount;
+        self.outflow_window_base = base;
+        if base == 0 {
+            return Some(true);
+        }
+        let outflow = window_amount.checked_add(amount)?;
+        if u128::from(outflow) > self.outflow_limit(base)? {
+            return Some(false);
+        }
+        self.outflow_window_amount = outflow;
+        Some(true)
+    }
+
+    /// whether the outflows of the current window keep `amount`, itself within the limit, from leaving the pool
+    pub fn outflow_limit_reached(&self, amount: u64, current_time: u64) -> Option<bool> {
+        if self.outflow_limit_bps == 0 {
+            return Some(false);
+        }
+        let (_, window_amount, base) = self.outflow_window_at(current_time)?;
+        let limit = self.outflow_limit(base)?;
+        let outflow = window_amount.checked_add(amount)?;
+        Some(u128::from(amount) <= limit && u128::from(outflow) > limit)
+    }
+
+    /// whether withdrawals are paused at `current_time`, by the pause flags or a tripped outflow limit
+    pub fn is_withdraw_paused(&self, current_time: u64) -> bool {
+        self.is_paused(PAUSE_WITHDRAW) || current_time < self.outflow_paused_until
+    }
+
+    /// early exit penalty on `amount` withdrawn or forfeited within the early exit window
+    pub fn early_exit_penalty(&self, amount: u64) -> Option<u64> {
+        u128::from(amount)
+            .checked_mul(self.early_exit_penalty_bps.into())?
+            .checked_div(MAX_FEE_BPS.into())?
+            .try_into()
+            .ok()
+    }
+
+    /// advances points_per_token_stored to `current_time`
+    pub fn checkpoint_points(&mut self, current_time: u64) -> Option<()> {
+        self.points_per_token_stored = points_per_token_at(self, current_time)?;
+        self.points_checkpoint_time = std::cmp::max(self.points_checkpoint_time, current_time);
+        Some(())
+    }
+
+    /// window of the current epoch, if an epoch has been started
+    pub fn current_epoch(&self) -> Option<EpochWindow> {
+        self.epochs
+            .iter()
+            .find(|w| w.epoch != 0 && w.epoch == self.epoch_count)
+            .copied()
+    }
+
+    /// advances epoch_stake_seconds, the stake-seconds of the current epoch, to `current_time`
+    pub fn checkpoint_epoch(&mut self, current_time: u64) -> Option<()> {
+        if let Some(window) = self.current_epoch() {
+            let stake_seconds = stake_seconds_in(
+                &window,
+                self.total_staked,
+                self.epoch_checkpoint_time,
+                current_time,
+            )?;
+            self.epoch_stake_seconds = self.epoch_stake_seconds.checked_add(stake_seconds)?;
+        }
+        self.epoch_checkpoint_time = std::cmp::max(self.epoch_checkpoint_time, current_time);
+        Some(())
+    }
+
+    /// makes `window` the current epoch, in place of the oldest one kept.
+    /// The pool must have been checkpointed to the current time.
+    pub fn push_epoch(&mut self, window: EpochWindow) -> Option<()> {
+        *self.epochs.iter_mut().min_by_key(|w| w.epoch)? = window;
+        self.epoch_count = window.epoch;
+        self.epoch_stake_seconds = 0;
+        self.epoch_finalized = false;
+        Some(())
+    }
+
     /// return reward a rate
     pub fn get_reward_a_rate(&self) -> u128 {
         if self.reward_a_rate_u128 == 0 {
@@ -1099,7 +3953,324 @@ impl Pool {
         return self.reward_a_rate_u128;
     }
 
+    /// whether an operation is paused. The legacy `paused` flag blocks deposits and funding.
+    pub fn is_paused(&self, flag: u8) -> bool {
+        if self.paused && flag & (PAUSE_DEPOSIT | PAUSE_FUND) != 0 {
+            return true;
+        }
+        self.pause_flags & flag != 0
+    }
+
+    /// window over which rewards accrue at `current_time`, once the reward period has started.
+    /// Rewards keep accruing after `reward_duration_end` until the budget runs out.
+    pub fn accrual_window(&self, current_time: u64) -> (u64, u64) {
+        let from = std::cmp::max(self.last_update_time, self.reward_start_time);
+        (from, std::cmp::max(from, current_time))
+    }
+
+    /// upper bound of the rewards that can still be credited to users up to `reward_duration_end`,
+    /// capped by the remaining budget
+    pub fn accruable_rewards(&self, current_time: u64) -> Option<u64> {
+        let (from, mut to) = self.accrual_window(current_time);
+        if self.reward_duration_end > 0 {
+            to = std::cmp::max(from, std::cmp::min(to, self.reward_duration_end));
+        }
+        let total_staked = std::cmp::max(self.total_staked, 1);
+        let accruable = base_reward(self, total_staked, total_staked, from, to)?
+            .checked_mul(MAX_REWARD_MULTIPLIER)?;
+        Some(std::cmp::min(accruable, self.total_reward))
+    }
+
+    /// return the length of reward periods
+    pub fn period_duration(&self) -> u64 {
+        if self.current_period_duration == 0 {
+            return self.reward_duration;
+        }
+        self.current_period_duration
+    }
+
+    /// whether an emission schedule has been configured
+    pub fn has_emission_schedule(&self) -> bool {
+        self.emission_segments.iter().any(|s| s.start_time != 0)
+    }
+
+    /// converts an amount of reward tokens in UI units to base units
+    pub fn reward_to_base_units(&self, amount: u128) -> Option<u128> {
+        to_base_units(amount, self.reward_decimals)
+    }
+
+    /// converts an amount of staking tokens in UI units to base units
+    pub fn staking_to_base_units(&self, amount: u128) -> Option<u128> {
+        to_base_units(amount, self.staking_decimals)
+    }
+
+    /// emission between two timestamps, scaled by PRECISION
+    pub fn emission_between(&self, from: u64, to: u64) -> Option<u128> {
+        integrate_emission(&self.emission_segments, self.get_reward_a_rate(), from, to)
+    }
+
+    /// emission between two timestamps at `total_staked`, scaled by PRECISION.
+    /// Follows the utilization curve when one is set and the emission schedule otherwise.
+    pub fn emission_at_stake(&self, from: u64, to: u64, total_staked: u64) -> Option<u128> {
+        if self.utilization_curve.is_set() {
+            let seconds = to.saturating_sub(from);
+            return self
+                .utilization_curve
+                .rate_at(total_staked)?
+                .checked_mul(seconds.into());
+        }
+        self.emission_between(from, to)
+    }
+
+}
+
+/// Admin action that goes through the pool timelock, with the arguments it will be executed with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminAction {
+    /// `authorize_funder` instruction
+    AuthorizeFunder {
+        /// Funder to authorize
+        funder: Pubkey,
+    },
+    /// `deauthorize_funder` instruction
+    DeauthorizeFunder {
+        /// Funder to deauthorize
+        funder: Pubkey,
+    },
+    /// `append_emission_segment` instruction
+    AppendEmissionSegment {
+        /// Timestamp from which the segment applies
+        start_time: u64,
+        /// Reward per second, scaled by PRECISION
+        rate: u128,
+        /// Seconds after which the rate halves
+        halving_interval: u64,
+    },
+    /// `set_reward_duration` instruction
+    SetRewardDuration {
+        /// New reward period length
+        duration: u64,
+    },
+    /// `set_min_stake_seconds` instruction
+    SetMinStakeSeconds {
+        /// New minimum stake duration
+        min_stake_seconds: u64,
+    },
+    /// `set_timelock_seconds` instruction
+    SetTimelockSeconds {
+        /// New timelock
+        timelock_seconds: u64,
+    },
+    /// `set_outflow_limit` instruction
+    SetOutflowLimit {
+        /// New outflow limit in basis points
+        limit_bps: u16,
+        /// New outflow window length
+        window_seconds: u64,
+    },
+    /// `set_early_exit_penalty` instruction
+    SetEarlyExitPenalty {
+        /// New early exit penalty in basis points
+        penalty_bps: u16,
+        /// New early exit window length
+        window_seconds: u64,
+    },
+    /// `set_utilization_curve` instruction
+    SetUtilizationCurve {
+        /// New utilization curve
+        curve: UtilizationCurve,
+    },
+    /// `set_points_rate` instruction
+    SetPointsRate {
+        /// New points per staked token per second, scaled by PRECISION
+        points_per_token_second: u128,
+        /// Whether token rewards stop being credited
+        points_only: bool,
+    },
+    /// `withdraw_extra_token` instruction
+    WithdrawExtraToken,
+    /// `withdraw_unallocated_rewards` instruction
+    WithdrawUnallocatedRewards,
+    /// `close_pool` instruction
+    ClosePool,
+    /// `force_close_user` instruction
+    ForceCloseUser {
+        /// User account to close
+        user: Pubkey,
+    },
+}
+
+/// Admin action waiting for the pool timelock
+#[account]
+pub struct QueuedAction {
+    /// Pool the action applies to
+    pub pool: Pubkey, // 32
+    /// Id picked by the authority, part of the seeds
+    pub action_id: u64, // 8
+    /// Action and its arguments
+    pub action: AdminAction, // 1 + 97
+    /// Timestamp at which the action was queued
+    pub queued_at: u64, // 8
+    /// Timestamp from which the action can be executed
+    pub eta: u64, // 8
+    /// Bump
+    pub bump: u8, // 1
+}
+
+impl QueuedAction {
+    /// Serialized size of the largest action, without discriminator
+    pub const LEN: usize = 155;
+}
+
+/// Segment of a pool emission schedule
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EmissionSegment {
+    /// Timestamp from which the segment applies
+    pub start_time: u64, // 8
+    /// Reward per second, scaled by PRECISION
+    pub rate: u128, // 16
+    /// Seconds after which the rate halves, 0 for a flat rate
+    pub halving_interval: u64, // 8
+}
+
+/// Point of a utilization curve
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CurvePoint {
+    /// Total staked at which the point applies
+    pub total_staked: u64, // 8
+    /// Reward per second to the whole pool, scaled by PRECISION
+    pub rate: u128, // 16
+}
+
+/// Piecewise-linear map from the pool total staked to its emission rate, in base units on the pool.
+/// Flat before the first and after the last point, a kink model is a three point curve.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UtilizationCurve {
+    /// Points by increasing total staked, slots from `len` on are unused and zeroed
+    pub points: [CurvePoint; MAX_CURVE_POINTS],
+    /// Number of points in use, 0 when the pool has no curve
+    pub len: u8,
+}
+
+impl UtilizationCurve {
+    /// whether the curve has been configured
+    pub fn is_set(&self) -> bool {
+        self.len > 0
+    }
+
+    /// whether the points are in use order, strictly increasing and the unused slots zeroed
+    pub fn is_valid(&self) -> bool {
+        let len = usize::from(self.len);
+        len <= MAX_CURVE_POINTS
+            && self.points[..len]
+                .windows(2)
+                .all(|w| w[0].total_staked < w[1].total_staked)
+            && self.points[len..]
+                .iter()
+                .all(|p| *p == CurvePoint::default())
+    }
+
+    /// emission rate at `total_staked`, interpolated between the surrounding points
+    pub fn rate_at(&self, total_staked: u64) -> Option<u128> {
+        let points = self.points.get(..usize::from(self.len))?;
+        let first = points.first()?;
+        if total_staked <= first.total_staked {
+            return Some(first.rate);
+        }
+        let upper_idx = match points.iter().position(|p| p.total_staked >= total_staked) {
+            Some(idx) => idx,
+            None => return points.last().map(|p| p.rate),
+        };
+        let lower = points.get(upper_idx.checked_sub(1)?)?;
+        let upper = points.get(upper_idx)?;
+        let span: u128 = upper.total_staked.checked_sub(lower.total_staked)?.into();
+        let offset: u128 = total_staked.checked_sub(lower.total_staked)?.into();
+        if upper.rate >= lower.rate {
+            let delta = upper.rate.checked_sub(lower.rate)?;
+            lower
+                .rate
+                .checked_add(delta.checked_mul(offset)?.checked_div(span)?)
+        } else {
+            let delta = lower.rate.checked_sub(upper.rate)?;
+            lower
+                .rate
+                .checked_sub(delta.checked_mul(offset)?.checked_div(span)?)
+        }
+    }
+}
+
+/// Timestamps of a pool epoch, kept on the pool for the most recent epochs
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochWindow {
+    /// Epoch number, 0 for an unused slot
+    pub epoch: u64, // 8
+    /// Timestamp at which the epoch starts
+    pub start_time: u64, // 8
+    /// Timestamp at which the epoch ends
+    pub end_time: u64, // 8
+}
+
+/// Stake-seconds of a user in an epoch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochStake {
+    /// Epoch number, 0 for an unused slot
+    pub epoch: u64, // 8
+    /// Staked amount times the seconds it was held within the epoch
+    pub stake_seconds: u128, // 16
+    /// Whether the epoch reward has been claimed
+    pub claimed: bool, // 1
+}
+
+/// Deposit of a user that may still be in the early exit window
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EarlyExitLot {
+    /// Amount deposited, less what has been withdrawn of it
+    pub amount: u64, // 8
+    /// Timestamp of the deposit
+    pub deposited_at: u64, // 8
+    /// Pending rewards earned by the lot that claims kept back, released when the window ends
+    pub withheld: u64, // 8
+}
+
+impl EarlyExitLot {
+    /// whether the lot is still in the early exit window of `window_seconds` at `current_time`
+    pub fn is_early(&self, window_seconds: u64, current_time: u64) -> bool {
+        self.amount > 0 && current_time < self.deposited_at.saturating_add(window_seconds)
+    }
+}
+
+/// Reward budget of a pool epoch, shared by stake-seconds once the epoch is finalized
+#[account]
+#[derive(Debug, Default)]
+pub struct Epoch {
+    /// Pool the epoch belongs to
+    pub pool: Pubkey, // 32
+    /// Epoch number, part of the seeds
+    pub epoch: u64, // 8
+    /// Reward A tokens shared by the stakers of the epoch
+    pub budget: u64, // 8
+    /// Timestamp at which the epoch starts
+    pub start_time: u64, // 8
+    /// Timestamp at which the epoch ends
+    pub end_time: u64, // 8
+    /// Stake-seconds of the whole pool over the epoch, set when finalized
+    pub total_stake_seconds: u128, // 16
+    /// Part of the budget claimed so far, or all of it once the rest has been released
+    pub claimed: u64, // 8
+    /// Whether total_stake_seconds is final, claims open once it is
+    pub finalized: bool, // 1
+    /// Bump
+    pub bump: u8, // 1
+}
+
+impl Epoch {
+    /// Serialized size, without discriminator
+    pub const LEN: usize = 90;
 
+    /// whether the claims of the epoch have closed at `current_time`
+    pub fn is_claim_period_over(&self, current_time: u64) -> bool {
+        current_time >= self.end_time.saturating_add(EPOCH_CLAIM_PERIOD)
+    }
 }
 
 /// Farming user account
@@ -1122,6 +4293,423 @@ pub struct User {
     pub balance_staked: u64,
     /// Signer nonce.
     pub nonce: u8,
+    /// Layout version, see USER_VERSION
+    pub version: u8,
+    /// Part of the stake deposited recently that earns no rewards until `matures_at`.
+    pub immature_balance: u64,
+    /// Timestamp at which the immature stake matures.
+    pub matures_at: u64,
+    /// Set by the pool authority to block deposits and claims. Withdrawals stay open.
+    pub frozen: bool,
+    /// Timestamp of the last deposit by the owner.
+    pub last_deposit_time: u64,
+    /// Points earned up to the last update. Points are tracked off-chain, no token backs them.
+    pub points: u128,
+    /// Pool points_per_token_stored at the last update.
+    pub points_per_token_complete: u128,
+    /// Time up to which stake-seconds have been recorded in epoch_stakes.
+    pub stake_seconds_at: u64,
+    /// Stake-seconds in the most recent epochs the user held a stake in.
+    pub epoch_stakes: [EpochStake; EPOCH_HISTORY],
+    /// Deposits that may still be in the early exit window.
+    pub early_exit_lots: [EarlyExitLot; EARLY_EXIT_LOTS],
+}
+
+impl User {
+    /// Serialized size of the current layout, without discriminator
+    pub const LEN: usize = 359;
+
+    /// fills in the fields added by each version up to USER_VERSION
+    pub fn migrate(&mut self) {
+        // the fields added up to version 6 are right at zero: a mature stake, not frozen, no deposit
+        // in the early exit window, and points and stake-seconds counted from the start,
+        // over which the stake was held
+        while self.version < USER_VERSION {
+            if self.version == 6 && self.last_deposit_time > 0 {
+                // the last deposit restarted the early exit window of the whole stake
+                self.early_exit_lots[0] = EarlyExitLot {
+                    amount: self.balance_staked,
+                    deposited_at: self.last_deposit_time,
+                    withheld: 0,
+                };
+            }
+            self.version = self.version.saturating_add(1);
+        }
+    }
+
+    /// splits the pending rewards of a force close into what `available` pays out, what a frozen user
+    /// forfeits to the reward budget and what an underfunded vault cannot cover.
+    /// Returns (reward_amount, reward_forfeited, reward_shortfall).
+    pub fn force_close_rewards(&self, available: u64) -> (u64, u64, u64) {
+        let pending = self.reward_a_per_token_pending;
+        if self.frozen {
+            return (0, pending, 0);
+        }
+        let reward_amount = std::cmp::min(pending, available);
+        (reward_amount, 0, pending.saturating_sub(reward_amount))
+    }
+
+    /// part of the stake deposited within the early exit window of `window_seconds` at `current_time`
+    pub fn early_exit_balance(&self, window_seconds: u64, current_time: u64) -> u64 {
+        let early: u64 = self
+            .early_exit_lots
+            .iter()
+            .filter(|lot| lot.is_early(window_seconds, current_time))
+            .fold(0, |sum, lot| sum.saturating_add(lot.amount));
+        std::cmp::min(early, self.balance_staked)
+    }
+
+    /// records a deposit of `amount` at `current_time`, dropping the lots past the window.
+    /// When every lot is taken the deposit joins the most recent one, whose window restarts
+    /// only if `restart` is set.
+    pub fn add_early_exit_lot(
+        &mut self,
+        amount: u64,
+        window_seconds: u64,
+        current_time: u64,
+        restart: bool,
+    ) -> Option<()> {
+        self.drop_late_early_exit_lots(window_seconds, current_time);
+        let lot = match self.early_exit_lots.iter().position(|lot| lot.amount == 0) {
+            Some(idx) => self.early_exit_lots.get_mut(idx)?,
+            None => self
+                .early_exit_lots
+                .iter_mut()
+                .max_by_key(|lot| lot.deposited_at)?,
+        };
+        if lot.amount == 0 || restart {
+            lot.deposited_at = current_time;
+        }
+        lot.amount = lot.amount.checked_add(amount)?;
+        Some(())
+    }
+
+    /// takes a withdrawal of `amount` out of the stake past the window first, then out of the most
+    /// recent lots, and returns the part of it deposited within the window along with the rewards
+    /// withheld for that part. Call before reducing the balance.
+    pub fn take_early_exit_lots(
+        &mut self,
+        amount: u64,
+        window_seconds: u64,
+        current_time: u64,
+    ) -> Option<(u64, u64)> {
+        let early_balance = self.early_exit_balance(window_seconds, current_time);
+        let late_balance = self.balance_staked.checked_sub(early_balance)?;
+        let early_amount = std::cmp::min(amount.saturating_sub(late_balance), early_balance);
+        let mut left = early_amount;
+        let mut withheld_taken: u64 = 0;
+        self.drop_late_early_exit_lots(window_seconds, current_time);
+        self.early_exit_lots
+            .sort_by_key(|lot| std::cmp::Reverse(lot.deposited_at));
+        for lot in self.early_exit_lots.iter_mut() {
+            let taken = std::cmp::min(left, lot.amount);
+            if taken == 0 {
+                continue;
+            }
+            let withheld = u128::from(lot.withheld)
+                .checked_mul(taken.into())?
+                .checked_div(lot.amount.into())?;
+            let withheld = u64::try_from(withheld).ok()?;
+            lot.withheld = lot.withheld.checked_sub(withheld)?;
+            lot.amount = lot.amount.checked_sub(taken)?;
+            withheld_taken = withheld_taken.checked_add(withheld)?;
+            left = left.saturating_sub(taken);
+        }
+        Some((early_amount, withheld_taken))
+    }
+
+    /// pending rewards kept back for the lots still in the early exit window of `window_seconds` at `current_time`
+    pub fn early_exit_withheld(&self, window_seconds: u64, current_time: u64) -> u64 {
+        let withheld: u64 = self
+            .early_exit_lots
+            .iter()
+            .filter(|lot| lot.is_early(window_seconds, current_time))
+            .fold(0, |sum, lot| sum.saturating_add(lot.withheld));
+        std::cmp::min(withheld, self.reward_a_per_token_pending)
+    }
+
+    /// keeps back the share of the pending rewards not yet withheld that the lots in the early exit window
+    /// earned, in proportion to their part of the stake, and returns the total withheld. The lots past the
+    /// window are dropped, which releases their rewards.
+    pub fn withhold_early_exit_rewards(
+        &mut self,
+        window_seconds: u64,
+        current_time: u64,
+    ) -> Option<u64> {
+        self.drop_late_early_exit_lots(window_seconds, current_time);
+        let free = self
+            .reward_a_per_token_pending
+            .saturating_sub(self.early_exit_withheld(window_seconds, current_time));
+        if free > 0 && self.balance_staked > 0 {
+            for lot in self.early_exit_lots.iter_mut() {
+                let share = u128::from(free)
+                    .checked_mul(lot.amount.into())?
+                    .checked_div(self.balance_staked.into())?;
+                lot.withheld = lot.withheld.checked_add(u64::try_from(share).ok()?)?;
+            }
+        }
+        Some(self.early_exit_withheld(window_seconds, current_time))
+    }
+
+    /// clears the lots past the early exit window of `window_seconds` at `current_time`
+    fn drop_late_early_exit_lots(&mut self, window_seconds: u64, current_time: u64) {
+        for lot in self.early_exit_lots.iter_mut() {
+            if !lot.is_early(window_seconds, current_time) {
+                *lot = EarlyExitLot::default();
+            }
+        }
+    }
+
+    /// whether the whole stake is still immature at `current_time`
+    pub fn is_immature(&self, current_time: u64) -> bool {
+        self.immature_balance > 0
+            && self.immature_balance >= self.balance_staked
+            && self.matures_at > current_time
+    }
+
+    /// records the stake-seconds held since `stake_seconds_at` within each of the pool `epochs`, up to `current_time`.
+    /// A new epoch takes the slot of the oldest record that is unused, claimed, or empty and older. While every
+    /// record still has a share to claim, the stake-seconds of newer epochs are not recorded.
+    pub fn record_stake_seconds(
+        &mut self,
+        mut epochs: [EpochWindow; EPOCH_HISTORY],
+        current_time: u64,
+    ) -> Option<()> {
+        epochs.sort_by_key(|w| w.epoch);
+        for window in epochs.iter() {
+            let stake_seconds = stake_seconds_in(
+                window,
+                self.balance_staked,
+                self.stake_seconds_at,
+                current_time,
+            )?;
+            if stake_seconds == 0 {
+                continue;
+            }
+            let record = match self.epoch_stakes.iter().position(|r| r.epoch == window.epoch) {
+                Some(idx) => self.epoch_stakes.get_mut(idx)?,
+                None => {
+                    let free = self
+                        .epoch_stakes
+                        .iter_mut()
+                        .filter(|r| {
+                            r.epoch == 0
+                                || r.claimed
+                                || (r.stake_seconds == 0 && r.epoch < window.epoch)
+                        })
+                        .min_by_key(|r| r.epoch);
+                    let oldest = match free {
+                        Some(oldest) if oldest.epoch <= window.epoch => oldest,
+                        _ => continue,
+                    };
+                    *oldest = EpochStake {
+                        epoch: window.epoch,
+                        ..EpochStake::default()
+                    };
+                    oldest
+                }
+            };
+            record.stake_seconds = record.stake_seconds.checked_add(stake_seconds)?;
+        }
+        self.stake_seconds_at = std::cmp::max(self.stake_seconds_at, current_time);
+        Some(())
+    }
+}
+
+/// Grows an account to `space` bytes, topping up its rent from the payer. Accounts are never shrunk.
+pub fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+) -> Result<()> {
+    if account.data_len() >= space {
+        return Ok(());
+    }
+    let rent = Rent::get()?.minimum_balance(space);
+    let lamports = rent.saturating_sub(account.lamports());
+    if lamports > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            lamports,
+        )?;
+    }
+    account.realloc(space, true)?;
+    Ok(())
+}
+
+/// Creates a program account of `space` bytes at the PDA signed for by `signer_seeds`, paid by `payer`.
+/// Lamports already sent to the address count towards its rent, as with `init`.
+pub fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+                &[signer_seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+    let lamports = rent.saturating_sub(account.lamports());
+    if lamports > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            lamports,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+/// Closes a program account of type `T` into `refundee`. Accounts that were never created are left alone.
+pub fn close_if_created<'info, T>(account: &AccountInfo<'info>, refundee: &AccountInfo<'info>) -> Result<()>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    if account.owner != &crate::ID {
+        return Ok(());
+    }
+    Account::<T>::try_from(account)?.close(refundee.clone())
+}
+
+/// Human-facing identity of a pool
+#[account]
+#[derive(Debug, Default)]
+pub struct PoolMetadata {
+    /// Pool the metadata belongs to
+    pub pool: Pubkey, // 32
+    /// Display name
+    pub name: String, // 4 + 32
+    /// Ticker-like symbol
+    pub symbol: String, // 4 + 10
+    /// URI of off-chain metadata
+    pub uri: String, // 4 + 200
+    /// Free-form tags
+    pub tags: Vec<String>, // 4 + 5 * (4 + 16)
+    /// Timestamp at which the metadata was created
+    pub created_at: u64, // 8
+    /// Bump
+    pub bump: u8, // 1
+}
+
+/// checks pool metadata fits in the metadata account
+pub fn validate_pool_metadata(name: &str, symbol: &str, uri: &str, tags: &[String]) -> Result<()> {
+    if name.len() > MAX_POOL_NAME_LEN
+        || symbol.len() > MAX_POOL_SYMBOL_LEN
+        || uri.len() > MAX_POOL_URI_LEN
+        || tags.iter().any(|tag| tag.len() > MAX_POOL_TAG_LEN)
+    {
+        return Err(ErrorCode::MetadataTooLong.into());
+    }
+    if tags.len() > MAX_POOL_TAGS {
+        return Err(ErrorCode::TooManyMetadataTags.into());
+    }
+    Ok(())
+}
+
+/// Program-wide config, a singleton PDA
+#[account]
+#[derive(Debug, Default)]
+pub struct GlobalConfig {
+    /// Program admin
+    pub admin: Pubkey, // 32
+    /// Default fee in basis points for new pools
+    pub default_fee_bps: u16, // 2
+    /// Default recipient of fees
+    pub fee_recipient: Pubkey, // 32
+    /// Staking mints allowed for new pools, empty slots are default pubkeys.
+    /// An empty allowlist allows any staking mint.
+    pub allowed_staking_mints: [Pubkey; MAX_ALLOWED_STAKING_MINTS], // 32 * 16 = 512
+    /// Bump
+    pub bump: u8, // 1
+}
+
+impl GlobalConfig {
+    /// whether a pool may be created for the staking mint
+    pub fn is_staking_mint_allowed(&self, mint: &Pubkey) -> bool {
+        let default_pubkey = Pubkey::default();
+        self.allowed_staking_mints.iter().all(|x| *x == default_pubkey)
+            || self.allowed_staking_mints.contains(mint)
+    }
+}
+
+/// Pools sharing a staking mint
+#[account]
+#[derive(Debug, Default)]
+pub struct PoolIndex {
+    /// Staking mint of the pools
+    pub staking_mint: Pubkey, // 32
+    /// Pools, empty slots are default pubkeys
+    pub pools: [Pubkey; MAX_INDEXED_POOLS], // 32 * 32 = 1024
+    /// Bump
+    pub bump: u8, // 1
+}
+
+impl PoolIndex {
+    /// Serialized size of the current layout, without discriminator
+    pub const LEN: usize = 1057;
+
+    /// add a pool to the first empty slot
+    pub fn add(&mut self, pool: Pubkey) -> Result<()> {
+        let default_pubkey = Pubkey::default();
+        let slot = self
+            .pools
+            .iter_mut()
+            .find(|x| **x == default_pubkey)
+            .ok_or(ErrorCode::PoolIndexFull)?;
+        *slot = pool;
+        Ok(())
+    }
+
+    /// remove a pool, pools missing from the index are ignored
+    pub fn remove(&mut self, pool: &Pubkey) {
+        if let Some(slot) = self.pools.iter_mut().find(|x| *x == pool) {
+            *slot = Pubkey::default();
+        }
+    }
 }
 
 
@@ -1135,45 +4723,606 @@ pub struct ChargeReward<'info> {
 #[derive(Accounts)]
 pub struct RemainingReward<'info> {
     pub pool: Account<'info, Pool>,
-    pub signer: Signer<'info>,
+}
+
+/// Accounts for [GetPoolStats](/dual_farming/instruction/struct.GetPoolStats.html) instruction
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    /// Global accounts for the staking instance.
+    pool: Box<Account<'info, Pool>>,
+}
+
+/// Accounts for [GetPendingRewards](/dual_farming/instruction/struct.GetPendingRewards.html)
+/// and [GetUserPosition](/dual_farming/instruction/struct.GetUserPosition.html) instructions.
+#[derive(Accounts)]
+pub struct GetUserPosition<'info> {
+    /// Global accounts for the staking instance.
+    pool: Box<Account<'info, Pool>>,
+    /// User
+    #[account(has_one = pool)]
+    user: Box<Account<'info, User>>,
+}
+
+/// Pool figures returned by `get_pool_stats`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Total staked amount
+    pub total_staked: u64,
+    /// Reward budget not credited to users yet
+    pub remaining_reward: u64,
+    /// Rewards credited to users and not claimed yet
+    pub total_pending: u64,
+    /// Rewards emitted per second to the whole pool, before tier multipliers
+    pub reward_rate: u64,
+    /// The timestamp at which the current reward period starts
+    pub reward_start_time: u64,
+    /// The timestamp at which the current reward period ends
+    pub reward_duration_end: u64,
+    /// Seconds left in the current reward period
+    pub time_left: u64,
+    /// Users staked
+    pub user_stake_count: u32,
+}
+
+/// User figures returned by `get_user_position`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserPosition {
+    /// The amount staked
+    pub balance_staked: u64,
+    /// Tier multiplier the next update would apply
+    pub multiplier: u64,
+    /// Rewards claimable now
+    pub pending: u64,
+    /// Part of the stake that has not matured yet
+    pub immature_balance: u64,
+    /// Timestamp at which the immature stake matures
+    pub matures_at: u64,
+    /// Whether the user is frozen
+    pub frozen: bool,
+    /// Points earned so far
+    pub points: u128,
+}
+
+/// position of a user as of now, without mutating the accounts
+fn user_position(pool: &Pool, user: &User) -> Result<UserPosition> {
+    let current_time: u64 = clock::Clock::get()?
+        .unix_timestamp
+        .try_into()
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    let (multiplier, accrued) =
+        user_accrued_reward(pool, user, pool.total_staked, current_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+    Ok(UserPosition {
+        balance_staked: user.balance_staked,
+        multiplier,
+        pending: user
+            .reward_a_per_token_pending
+            .checked_add(accrued)
+            .ok_or(ErrorCode::MathOverflow)?,
+        immature_balance: if user.matures_at > current_time {
+            user.immature_balance
+        } else {
+            0
+        },
+        matures_at: user.matures_at,
+        frozen: user.frozen,
+        points: user_points(pool, user, current_time).ok_or(ErrorCode::MathOverflow)?,
+    })
+}
+
+/// Pool initialized event
+#[event]
+pub struct EventInitializePool {
+    pool: Pubkey,
+    authority: Pubkey,
+    staking_mint: Pubkey,
+    reward_a_mint: Pubkey,
+    reward_duration: u64,
+    total_reward: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// User created event
+#[event]
+pub struct EventCreateUser {
+    pool: Pubkey,
+    owner: Pubkey,
+    user: Pubkey,
+    user_stake_count: u32,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Closed user event
+#[event]
+pub struct EventCloseUser {
+    pool: Pubkey,
+    owner: Pubkey,
+    user: Pubkey,
+    user_stake_count: u32,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Closed pool event
+#[event]
+pub struct EventClosePool {
+    pool: Pubkey,
+    authority: Pubkey,
+    staking_refund: u64,
+    reward_a_refund: u64,
+    total_reward: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Charged reward event
+#[event]
+pub struct EventChargeReward {
+    pool: Pubkey,
+    signer: Pubkey,
+    amount: u64,
+    total_reward_before: u64,
+    total_reward: u64,
+    total_staked: u64,
+    slot: u64,
+    timestamp: i64,
 }
 
 /// Deposit event
 #[event]
 pub struct EventDeposit {
+    pool: Pubkey,
+    owner: Pubkey,
+    payer: Pubkey,
     amount: u64,
+    balance_before: u64,
+    balance_after: u64,
+    reward_pending: u64,
+    total_staked: u64,
+    total_reward: u64,
+    slot: u64,
+    timestamp: i64,
 }
 
 /// Withdraw event
 #[event]
 pub struct EventWithdraw {
+    pool: Pubkey,
+    owner: Pubkey,
     amount: u64,
+    /// early exit penalty, in staking tokens when the mints match, otherwise in forfeited reward tokens
+    penalty: u64,
+    balance_before: u64,
+    balance_after: u64,
+    reward_pending: u64,
+    total_staked: u64,
+    total_reward: u64,
+    slot: u64,
+    timestamp: i64,
 }
 
 /// Fund event
 #[event]
 pub struct EventFund {
+    pool: Pubkey,
+    funder: Pubkey,
     amount_a: u64,
+    start_time: u64,
+    reward_duration_end: u64,
+    reward_a_rate: u128,
+    total_reward_before: u64,
+    total_reward: u64,
+    total_staked: u64,
+    slot: u64,
+    timestamp: i64,
     // amount_b: u64,
 }
 
 /// Claim event
 #[event]
 pub struct EventClaim {
+    pool: Pubkey,
+    owner: Pubkey,
     amount_a: u64,
     // amount_b: u64,
+    pending_before: u64,
+    pending_after: u64,
+    balance_staked: u64,
+    total_staked: u64,
+    total_reward: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Epoch started event
+#[event]
+pub struct EventStartEpoch {
+    pool: Pubkey,
+    funder: Pubkey,
+    epoch: u64,
+    budget: u64,
+    start_time: u64,
+    end_time: u64,
+    epoch_reserved: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Epoch finalized event
+#[event]
+pub struct EventFinalizeEpoch {
+    pool: Pubkey,
+    caller: Pubkey,
+    epoch: u64,
+    budget: u64,
+    total_stake_seconds: u128,
+    epoch_reserved: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Epoch claim event
+#[event]
+pub struct EventClaimEpoch {
+    pool: Pubkey,
+    owner: Pubkey,
+    epoch: u64,
+    amount: u64,
+    stake_seconds: u128,
+    total_stake_seconds: u128,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Epoch swept event
+#[event]
+pub struct EventSweepEpoch {
+    pool: Pubkey,
+    caller: Pubkey,
+    epoch: u64,
+    released: u64,
+    epoch_reserved: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Extra staking token withdrawn event
+#[event]
+pub struct EventWithdrawExtraToken {
+    pool: Pubkey,
+    authority: Pubkey,
+    amount: u64,
+    vault_balance_before: u64,
+    vault_balance_after: u64,
+    total_staked: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Emission segment appended event
+#[event]
+pub struct EventAppendEmissionSegment {
+    pool: Pubkey,
+    authority: Pubkey,
+    start_time: u64,
+    rate: u128,
+    halving_interval: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Unallocated rewards withdrawn event
+#[event]
+pub struct EventWithdrawUnallocatedRewards {
+    pool: Pubkey,
+    authority: Pubkey,
+    amount: u64,
+    total_pending: u64,
+    accruable: u64,
+    total_reward_before: u64,
+    total_reward: u64,
+    total_staked: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Force closed user event
+#[event]
+pub struct EventForceCloseUser {
+    pool: Pubkey,
+    authority: Pubkey,
+    owner: Pubkey,
+    staked_amount: u64,
+    reward_amount: u64,
+    reward_forfeited: u64,
+    reward_shortfall: u64,
+    user_stake_count: u32,
+    total_staked: u64,
+    total_reward: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Pool accounting snapshot event
+#[event]
+pub struct EventCheckpoint {
+    pool: Pubkey,
+    caller: Pubkey,
+    reward_a_per_token_stored: u128,
+    points_per_token_stored: u128,
+    epoch_stake_seconds: u128,
+    reward_a_rate: u128,
+    total_staked: u64,
+    total_reward: u64,
+    total_pending: u64,
+    last_update_time: u64,
+    reward_duration_end: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Pause event
+#[event]
+pub struct EventPause {
+    pool: Pubkey,
+    authority: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Unpause event
+#[event]
+pub struct EventUnpause {
+    pool: Pubkey,
+    authority: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Pause flags change event
+#[event]
+pub struct EventSetPauseFlags {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_pause_flags: u8,
+    new_pause_flags: u8,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// User frozen event
+#[event]
+pub struct EventFreezeUser {
+    pool: Pubkey,
+    user: Pubkey,
+    owner: Pubkey,
+    authority: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// User unfrozen event
+#[event]
+pub struct EventUnfreezeUser {
+    pool: Pubkey,
+    user: Pubkey,
+    owner: Pubkey,
+    authority: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Minimum stake duration change event
+#[event]
+pub struct EventSetMinStakeSeconds {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_min_stake_seconds: u64,
+    new_min_stake_seconds: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Timelock change event
+#[event]
+pub struct EventSetTimelockSeconds {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_timelock_seconds: u64,
+    new_timelock_seconds: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Outflow limit change event
+#[event]
+pub struct EventSetOutflowLimit {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_limit_bps: u16,
+    new_limit_bps: u16,
+    old_window_seconds: u64,
+    new_window_seconds: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Early exit penalty change event
+#[event]
+pub struct EventSetEarlyExitPenalty {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_penalty_bps: u16,
+    new_penalty_bps: u16,
+    old_window_seconds: u64,
+    new_window_seconds: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Utilization curve change event
+#[event]
+pub struct EventSetUtilizationCurve {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_curve: UtilizationCurve,
+    new_curve: UtilizationCurve,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Points rate change event
+#[event]
+pub struct EventSetPointsRate {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_points_per_token_second: u128,
+    new_points_per_token_second: u128,
+    points_only: bool,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Outflow limit tripped alert, withdrawals have been paused
+#[event]
+pub struct EventOutflowLimitTripped {
+    pool: Pubkey,
+    caller: Pubkey,
+    amount: u64,
+    window_amount: u64,
+    window_base: u64,
+    limit_bps: u16,
+    window_start: u64,
+    paused_until: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Admin action queued event
+#[event]
+pub struct EventQueueAction {
+    pool: Pubkey,
+    authority: Pubkey,
+    queued_action: Pubkey,
+    action_id: u64,
+    action: AdminAction,
+    eta: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Queued admin action canceled event
+#[event]
+pub struct EventCancelAction {
+    pool: Pubkey,
+    authority: Pubkey,
+    queued_action: Pubkey,
+    action_id: u64,
+    action: AdminAction,
+    eta: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Queued admin action executed event
+#[event]
+pub struct EventExecuteAction {
+    pool: Pubkey,
+    authority: Pubkey,
+    queued_action: Pubkey,
+    action_id: u64,
+    action: AdminAction,
+    eta: u64,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Reward duration change event
+#[event]
+pub struct EventSetRewardDuration {
+    pool: Pubkey,
+    authority: Pubkey,
+    old_duration: u64,
+    new_duration: u64,
+    reward_duration_end: u64,
+    reward_a_rate: u128,
+    slot: u64,
+    timestamp: i64,
 }
 
 /// Authorized funder event
 #[event]
 pub struct EventAuthorizeFunder {
+    pool: Pubkey,
+    authority: Pubkey,
     new_funder: Pubkey,
+    slot: u64,
+    timestamp: i64,
 }
 
 /// Un-authorized funder event
 #[event]
 pub struct EventUnauthorizeFunder {
+    pool: Pubkey,
+    authority: Pubkey,
     funder: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Account migration event
+#[event]
+pub struct EventMigrate {
+    pool: Pubkey,
+    account: Pubkey,
+    payer: Pubkey,
+    old_version: u8,
+    new_version: u8,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Pool metadata update event
+#[event]
+pub struct EventSetPoolMetadata {
+    pool: Pubkey,
+    authority: Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Global config update event
+#[event]
+pub struct EventUpdateGlobalConfig {
+    admin: Pubkey,
+    new_admin: Pubkey,
+    default_fee_bps: u16,
+    fee_recipient: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Staking mint allowed event
+#[event]
+pub struct EventAllowStakingMint {
+    admin: Pubkey,
+    mint: Pubkey,
+    slot: u64,
+    timestamp: i64,
+}
+
+/// Staking mint disallowed event
+#[event]
+pub struct EventDisallowStakingMint {
+    admin: Pubkey,
+    mint: Pubkey,
+    slot: u64,
+    timestamp: i64,
 }
 
 /// Program error codes
@@ -1209,6 +5358,141 @@ pub enum ErrorCode {
     /// Math opeartion overflow
     #[msg("Math operation overflow")]
     MathOverflow,
+    /// Emission segment must start in the future.
+    #[msg("Emission segment must start in the future.")]
+    EmissionSegmentNotInFuture,
+    /// Emission segment must start after the existing segments.
+    #[msg("Emission segment must start after the existing segments.")]
+    EmissionSegmentOutOfOrder,
+    /// Maximum emission segments already scheduled.
+    #[msg("Maximum emission segments already scheduled.")]
+    MaxEmissionSegments,
+    /// Reward start time cannot be in the past.
+    #[msg("Reward start time cannot be in the past.")]
+    RewardStartTimeInPast,
+    /// A reward period is already in progress.
+    #[msg("A reward period is already in progress.")]
+    RewardPeriodInProgress,
+    /// Unknown pause flags.
+    #[msg("Unknown pause flags.")]
+    InvalidPauseFlags,
+    /// Deposits are paused.
+    #[msg("Deposits are paused.")]
+    DepositPaused,
+    /// Withdrawals are paused.
+    #[msg("Withdrawals are paused.")]
+    WithdrawPaused,
+    /// Claims are paused.
+    #[msg("Claims are paused.")]
+    ClaimPaused,
+    /// Funding is paused.
+    #[msg("Funding is paused.")]
+    FundPaused,
+    /// Staking mint is not allowed.
+    #[msg("Staking mint is not allowed.")]
+    StakingMintNotAllowed,
+    /// Staking mint is already allowed.
+    #[msg("Staking mint is already allowed.")]
+    StakingMintAlreadyAllowed,
+    /// Maximum staking mints already allowed.
+    #[msg("Maximum staking mints already allowed.")]
+    MaxAllowedStakingMints,
+    /// Metadata field is too long.
+    #[msg("Metadata field is too long.")]
+    MetadataTooLong,
+    /// Too many metadata tags.
+    #[msg("Too many metadata tags.")]
+    TooManyMetadataTags,
+    /// Remaining accounts must be groups of pool, user, reward vault and destination.
+    #[msg("Remaining accounts must be groups of pool, user, reward vault and destination.")]
+    InvalidRemainingAccounts,
+    /// Token account mint does not match the pool staking mint.
+    #[msg("Token account mint does not match the pool staking mint.")]
+    InvalidStakingMint,
+    /// Token account mint does not match the pool reward mint.
+    #[msg("Token account mint does not match the pool reward mint.")]
+    InvalidRewardMint,
+    /// Token account is not owned by the signer.
+    #[msg("Token account is not owned by the signer.")]
+    InvalidTokenAccountOwner,
+    /// Refundee must be the pool authority.
+    #[msg("Refundee must be the pool authority.")]
+    InvalidRefundee,
+    /// Stake has not been held for the pool minimum stake duration yet.
+    #[msg("Stake has not been held for the pool minimum stake duration yet.")]
+    StakeNotMatured,
+    /// Action must be queued and executed through the pool timelock.
+    #[msg("Action must be queued and executed through the pool timelock.")]
+    TimelockRequired,
+    /// Queued action does not match the instruction.
+    #[msg("Queued action does not match the instruction.")]
+    QueuedActionMismatch,
+    /// Queued action cannot be executed before its ETA.
+    #[msg("Queued action cannot be executed before its ETA.")]
+    TimelockNotExpired,
+    /// Timelock cannot exceed 30 days.
+    #[msg("Timelock cannot exceed 30 days.")]
+    TimelockTooLong,
+    /// Outflow limit cannot exceed 10000 basis points and needs a window.
+    #[msg("Outflow limit cannot exceed 10000 basis points and needs a window.")]
+    InvalidOutflowLimit,
+    /// User is frozen
+    #[msg("User is frozen.")]
+    UserFrozen,
+    /// Early exit penalty cannot exceed 10000 basis points.
+    #[msg("Early exit penalty cannot exceed 10000 basis points.")]
+    InvalidEarlyExitPenalty,
+    /// Utilization curve points must increase in total staked, with the unused slots zeroed.
+    #[msg("Utilization curve points must increase in total staked, with the unused slots zeroed.")]
+    InvalidUtilizationCurve,
+    /// Epoch number does not match the pool epochs.
+    #[msg("Epoch number does not match the pool epochs.")]
+    InvalidEpoch,
+    /// Epoch must start now or later and end after its start.
+    #[msg("Epoch must start now or later and end after its start.")]
+    InvalidEpochWindow,
+    /// Epoch has not been finalized.
+    #[msg("Epoch has not been finalized.")]
+    EpochNotFinalized,
+    /// Epoch has already been finalized.
+    #[msg("Epoch has already been finalized.")]
+    EpochAlreadyFinalized,
+    /// Epoch has not ended yet.
+    #[msg("Epoch has not ended yet.")]
+    EpochNotEnded,
+    /// User has no stake-seconds recorded in the epoch.
+    #[msg("User has no stake-seconds recorded in the epoch.")]
+    NoEpochStake,
+    /// Epoch reward has already been claimed.
+    #[msg("Epoch reward has already been claimed.")]
+    EpochAlreadyClaimed,
+    /// User account belongs to another pool.
+    #[msg("User account belongs to another pool.")]
+    InvalidUserPool,
+    /// Users created before pending rewards were tracked must be migrated first.
+    #[msg("Users created before pending rewards were tracked must be migrated first.")]
+    UsersNotMigrated,
+    /// Signer is not the program upgrade authority.
+    #[msg("Signer is not the program upgrade authority.")]
+    NotUpgradeAuthority,
+    /// Outflow would go over the pool outflow limit.
+    #[msg("Outflow would go over the pool outflow limit.")]
+    OutflowLimitExceeded,
+    /// Reward A vault is required to take the early exit penalty.
+    #[msg("Reward A vault is required to take the early exit penalty.")]
+    RewardVaultRequired,
+    /// Epoch claim period is over.
+    #[msg("Epoch claim period is over.")]
+    EpochClaimPeriodOver,
+    /// Epoch claim period is not over.
+    #[msg("Epoch claim period is not over.")]
+    EpochClaimPeriodNotOver,
+    /// Fee basis points are above the maximum.
+    #[msg("Fee basis points are above the maximum.")]
+    InvalidFeeBps,
+    /// Pool index is full.
+    #[msg("Pool index is full.")]
+    PoolIndexFull,
 }
 
 impl Debug for User {
@@ -1249,4 +5533,434 @@ mod pool_test {
         );
         println!("pool_pda {}", pool_pda);
     }
+
+    #[test]
+    fn test_empty_allowlist_allows_any_staking_mint() {
+        let mint = Pubkey::new_unique();
+        let mut global_config = GlobalConfig::default();
+        assert!(global_config.is_staking_mint_allowed(&mint));
+
+        global_config.allowed_staking_mints[1] = Pubkey::new_unique();
+        assert!(!global_config.is_staking_mint_allowed(&mint));
+
+        global_config.allowed_staking_mints[3] = mint;
+        assert!(global_config.is_staking_mint_allowed(&mint));
+    }
+
+    #[test]
+    fn test_pool_index_reuses_empty_slots() {
+        let mut pool_index = PoolIndex::default();
+        let pools: Vec<Pubkey> = (0..MAX_INDEXED_POOLS).map(|_| Pubkey::new_unique()).collect();
+        for pool in pools.iter() {
+            pool_index.add(*pool).unwrap();
+        }
+        assert!(pool_index.add(Pubkey::new_unique()).is_err());
+
+        pool_index.remove(&pools[5]);
+        pool_index.remove(&Pubkey::new_unique());
+        let pool = Pubkey::new_unique();
+        pool_index.add(pool).unwrap();
+        assert_eq!(pool_index.pools[5], pool);
+    }
+
+    #[test]
+    fn test_account_len_matches_layout() {
+        assert_eq!(Pool::default().try_to_vec().unwrap().len(), Pool::LEN);
+        assert_eq!(User::default().try_to_vec().unwrap().len(), User::LEN);
+        let queued_action = QueuedAction {
+            pool: Pubkey::default(),
+            action_id: 0,
+            action: AdminAction::SetUtilizationCurve {
+                curve: UtilizationCurve::default(),
+            },
+            queued_at: 0,
+            eta: 0,
+            bump: 0,
+        };
+        assert_eq!(queued_action.try_to_vec().unwrap().len(), QueuedAction::LEN);
+        assert_eq!(Epoch::default().try_to_vec().unwrap().len(), Epoch::LEN);
+        assert_eq!(PoolIndex::default().try_to_vec().unwrap().len(), PoolIndex::LEN);
+    }
+
+    #[test]
+    fn test_legacy_pool_migrates_to_current_version() {
+        let mut pool = Pool {
+            reward_duration: 10,
+            _reward_a_rate: 7,
+            user_stake_count: 3,
+            ..Pool::default()
+        };
+        pool.migrate(6, 9);
+        assert_eq!(pool.version, POOL_VERSION);
+        assert_eq!(pool.reward_a_rate_u128, 7);
+        assert_eq!(pool.current_period_duration, 10);
+        assert_eq!(pool.legacy_user_count, 3);
+        assert_eq!(pool.staking_decimals, 6);
+        assert_eq!(pool.reward_decimals, 9);
+
+        let mut pool = Pool {
+            version: 1,
+            last_update_time: 42,
+            user_stake_count: 3,
+            ..Pool::default()
+        };
+        pool.migrate(6, 9);
+        assert_eq!(pool.version, POOL_VERSION);
+        assert_eq!(pool.checkpoint_time, 42);
+        // users of versioned pools are counted in total_pending from the start
+        assert_eq!(pool.legacy_user_count, 0);
+
+        // a short legacy account deserializes once zero padded
+        let mut data = <Pool as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+        data.extend(Pool::default().try_to_vec().unwrap());
+        data.truncate(8 + 494);
+        data.resize(8 + Pool::LEN, 0);
+        let legacy = Pool::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(legacy.version, 0);
+    }
+
+    #[test]
+    fn test_legacy_user_migrates_to_current_version() {
+        let mut pool = Pool {
+            legacy_user_count: 1,
+            total_pending: 5,
+            ..Pool::default()
+        };
+        // baseline user accounts were 200 bytes, zero padded once grown
+        let legacy = User {
+            reward_a_per_token_pending: 7,
+            balance_staked: 9,
+            nonce: 254,
+            ..User::default()
+        };
+        let mut data = <User as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+        data.extend(legacy.try_to_vec().unwrap());
+        data.truncate(200);
+        data.resize(8 + User::LEN, 0);
+        let mut user = User::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(user.version, 0);
+
+        assert_eq!(migrate_user_account(&mut pool, &mut user).unwrap(), 0);
+        assert_eq!(user.version, USER_VERSION);
+        assert_eq!(user.balance_staked, 9);
+        assert_eq!(pool.total_pending, 12);
+        assert_eq!(pool.legacy_user_count, 0);
+
+        // a current user is counted in already
+        assert_eq!(migrate_user_account(&mut pool, &mut user).unwrap(), USER_VERSION);
+        assert_eq!(pool.total_pending, 12);
+    }
+
+    #[test]
+    fn test_user_migration_keeps_the_early_exit_window() {
+        let mut user = User {
+            version: 6,
+            balance_staked: 9,
+            last_deposit_time: 10,
+            ..User::default()
+        };
+        user.migrate();
+        assert_eq!(user.version, USER_VERSION);
+        assert_eq!(user.early_exit_balance(100, 50), 9);
+        assert_eq!(user.early_exit_balance(100, 110), 0);
+        assert_eq!(user.early_exit_withheld(100, 50), 0);
+    }
+
+    #[test]
+    fn test_force_close_user_is_timelocked() {
+        let pool = Pool {
+            timelock_seconds: 10,
+            ..Pool::default()
+        };
+        let action = AdminAction::ForceCloseUser {
+            user: Pubkey::new_unique(),
+        };
+        assert_eq!(
+            check_timelock(&pool, None, action, Pubkey::default()).unwrap_err(),
+            ErrorCode::TimelockRequired.into()
+        );
+        assert!(check_timelock(&Pool::default(), None, action, Pubkey::default()).is_ok());
+    }
+
+    #[test]
+    fn test_outflow_limit_window() {
+        let mut pool = Pool {
+            total_staked: 1_000,
+            outflow_limit_bps: 2_500,
+            outflow_window_seconds: 100,
+            ..Pool::default()
+        };
+        assert_eq!(pool.record_outflow(200, 10), Some(true));
+        assert_eq!(pool.record_outflow(50, 50), Some(true));
+        // over 25% of the stake within the window, nothing recorded
+        assert_eq!(pool.record_outflow(1, 60), Some(false));
+        assert_eq!(pool.outflow_window_amount, 250);
+
+        // a new window starts once the previous one has elapsed
+        pool.total_staked = 750;
+        assert_eq!(pool.record_outflow(150, 110), Some(true));
+        assert_eq!(pool.outflow_window_start, 110);
+        assert_eq!(pool.outflow_window_base, 750);
+        assert_eq!(pool.record_outflow(50, 120), Some(false));
+
+        pool.outflow_limit_bps = 0;
+        assert_eq!(pool.record_outflow(u64::MAX, 130), Some(true));
+    }
+
+    #[test]
+    fn test_outflow_limit_reached() {
+        let mut pool = Pool {
+            total_staked: 1_000,
+            outflow_limit_bps: 2_500,
+            outflow_window_seconds: 100,
+            ..Pool::default()
+        };
+        // an amount over the limit by itself does not trip it
+        assert_eq!(pool.outflow_limit_reached(300, 10), Some(false));
+        assert_eq!(pool.record_outflow(200, 10), Some(true));
+        assert_eq!(pool.outflow_limit_reached(50, 20), Some(false));
+        assert_eq!(pool.outflow_limit_reached(51, 20), Some(true));
+        assert_eq!(pool.outflow_limit_reached(251, 20), Some(false));
+        // nor once the window has ended
+        assert_eq!(pool.outflow_limit_reached(51, 110), Some(false));
+
+        pool.outflow_paused_until = 110;
+        assert!(pool.is_withdraw_paused(109));
+        assert!(!pool.is_withdraw_paused(110));
+    }
+
+    #[test]
+    fn test_early_exit_penalty() {
+        let pool = Pool {
+            early_exit_penalty_bps: 500,
+            early_exit_window_seconds: 100,
+            ..Pool::default()
+        };
+        assert_eq!(pool.early_exit_penalty(1_000), Some(50));
+        assert_eq!(Pool::default().early_exit_penalty(1_000), Some(0));
+    }
+
+    #[test]
+    fn test_force_close_rewards() {
+        let mut user = User {
+            reward_a_per_token_pending: 100,
+            ..User::default()
+        };
+        assert_eq!(user.force_close_rewards(150), (100, 0, 0));
+        assert_eq!(user.force_close_rewards(60), (60, 0, 40));
+
+        // a frozen user forfeits everything, whatever the vault holds
+        user.frozen = true;
+        assert_eq!(user.force_close_rewards(150), (0, 100, 0));
+        assert_eq!(user.force_close_rewards(0), (0, 100, 0));
+    }
+
+    #[test]
+    fn test_early_exit_window_per_deposit() {
+        let mut user = User {
+            balance_staked: 100,
+            ..User::default()
+        };
+        user.add_early_exit_lot(100, 100, 10, true).unwrap();
+        user.balance_staked = 150;
+        user.add_early_exit_lot(50, 100, 60, true).unwrap();
+        assert_eq!(user.early_exit_balance(100, 60), 150);
+        // the first deposit leaves the window on its own
+        assert_eq!(user.early_exit_balance(100, 110), 50);
+
+        // the stake past the window is withdrawn first, then the most recent deposit
+        assert_eq!(user.take_early_exit_lots(130, 100, 110), Some((30, 0)));
+        assert_eq!(user.early_exit_balance(100, 110), 20);
+        assert_eq!(user.take_early_exit_lots(10, 100, 160), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_early_exit_rewards_withheld_per_deposit() {
+        let mut user = User {
+            balance_staked: 100,
+            reward_a_per_token_pending: 40,
+            ..User::default()
+        };
+        user.add_early_exit_lot(50, 100, 10, true).unwrap();
+        user.add_early_exit_lot(25, 100, 60, true).unwrap();
+        // a claim keeps back the share of the stake in its window, the rest can be claimed
+        assert_eq!(user.withhold_early_exit_rewards(100, 60), Some(30));
+        // later rewards are shared out again, what is withheld already stays
+        user.reward_a_per_token_pending = 70;
+        assert_eq!(user.withhold_early_exit_rewards(100, 60), Some(60));
+        user.reward_a_per_token_pending = 60;
+
+        // a withdrawal takes the rewards withheld for the stake it takes
+        user.balance_staked = 75;
+        assert_eq!(user.take_early_exit_lots(60, 100, 70), Some((60, 48)));
+        assert_eq!(user.early_exit_withheld(100, 70), 12);
+        // the first deposit leaving the window releases its rewards
+        assert_eq!(user.early_exit_withheld(100, 110), 0);
+        assert_eq!(user.withhold_early_exit_rewards(100, 110), Some(0));
+    }
+
+    #[test]
+    fn test_early_exit_lots_merge_into_the_latest() {
+        let mut user = User::default();
+        for time in 1..=(EARLY_EXIT_LOTS as u64 + 1) {
+            user.add_early_exit_lot(10, 100, time, true).unwrap();
+        }
+        user.balance_staked = 10 * (EARLY_EXIT_LOTS as u64 + 1);
+        assert_eq!(user.early_exit_balance(100, 101), 10 * EARLY_EXIT_LOTS as u64);
+        assert_eq!(
+            user.early_exit_lots.iter().map(|lot| lot.deposited_at).max(),
+            Some(EARLY_EXIT_LOTS as u64 + 1)
+        );
+
+        // someone else's deposit joins the latest lot without restarting its window
+        user.add_early_exit_lot(10, 100, 50, false).unwrap();
+        user.balance_staked = 10 * (EARLY_EXIT_LOTS as u64 + 2);
+        assert_eq!(
+            user.early_exit_lots.iter().map(|lot| lot.deposited_at).max(),
+            Some(EARLY_EXIT_LOTS as u64 + 1)
+        );
+        assert_eq!(user.early_exit_balance(100, 101), 10 * EARLY_EXIT_LOTS as u64 + 10);
+    }
+
+    #[test]
+    fn test_epoch_stake_seconds_add_up() {
+        let mut pool = Pool {
+            total_staked: 10,
+            ..Pool::default()
+        };
+        // staked before epochs existed
+        let mut early = User {
+            balance_staked: 10,
+            ..User::default()
+        };
+        let mut late = User::default();
+
+        pool.checkpoint_epoch(90).unwrap();
+        let window = |epoch, start_time, end_time| EpochWindow {
+            epoch,
+            start_time,
+            end_time,
+        };
+        pool.push_epoch(window(1, 100, 200)).unwrap();
+
+        // late deposits 30 at 150
+        pool.checkpoint_epoch(150).unwrap();
+        late.record_stake_seconds(pool.epochs, 150).unwrap();
+        late.balance_staked = 30;
+        pool.total_staked = 40;
+
+        pool.checkpoint_epoch(250).unwrap();
+        early.record_stake_seconds(pool.epochs, 250).unwrap();
+        late.record_stake_seconds(pool.epochs, 250).unwrap();
+        assert_eq!(pool.epoch_stake_seconds, 2_500);
+        assert_eq!(early.epoch_stakes[0].stake_seconds, 1_000);
+        assert_eq!(late.epoch_stakes[0].stake_seconds, 1_500);
+
+        // idle users pick up the epochs still kept on the pool, a claimed record makes room
+        for epoch in 2..=5 {
+            let start_time = epoch * 100 + 200;
+            pool.push_epoch(window(epoch, start_time, start_time + 50)).unwrap();
+        }
+        early.epoch_stakes[0].claimed = true;
+        early.record_stake_seconds(pool.epochs, 1_000).unwrap();
+        let mut epochs: Vec<u64> = early.epoch_stakes.iter().map(|r| r.epoch).collect();
+        epochs.sort_unstable();
+        assert_eq!(epochs, vec![2, 3, 4, 5]);
+        assert!(early.epoch_stakes.iter().all(|r| r.stake_seconds == 500));
+
+        // an unclaimed record is kept, the newest epoch goes unrecorded
+        late.record_stake_seconds(pool.epochs, 1_000).unwrap();
+        let mut epochs: Vec<u64> = late.epoch_stakes.iter().map(|r| r.epoch).collect();
+        epochs.sort_unstable();
+        assert_eq!(epochs, vec![1, 2, 3, 4]);
+        assert_eq!(late.epoch_stakes[0].stake_seconds, 1_500);
+    }
+
+    #[test]
+    fn test_epoch_claim_period() {
+        let epoch = Epoch {
+            end_time: 100,
+            ..Epoch::default()
+        };
+        assert!(!epoch.is_claim_period_over(100 + EPOCH_CLAIM_PERIOD - 1));
+        assert!(epoch.is_claim_period_over(100 + EPOCH_CLAIM_PERIOD));
+    }
+
+    #[test]
+    fn test_pool_metadata_limits() {
+        let tag = "t".repeat(MAX_POOL_TAG_LEN);
+        let tags = vec![tag; MAX_POOL_TAGS];
+        let name = "n".repeat(MAX_POOL_NAME_LEN);
+        assert!(validate_pool_metadata(&name, "SYM", "https://x", &tags).is_ok());
+
+        let long_name = "n".repeat(MAX_POOL_NAME_LEN + 1);
+        assert!(validate_pool_metadata(&long_name, "SYM", "https://x", &tags).is_err());
+        let long_tag = vec!["t".repeat(MAX_POOL_TAG_LEN + 1)];
+        assert!(validate_pool_metadata("name", "SYM", "https://x", &long_tag).is_err());
+        let more_tags = vec![String::new(); MAX_POOL_TAGS + 1];
+        assert!(validate_pool_metadata("name", "SYM", "https://x", &more_tags).is_err());
+    }
+
+    #[test]
+    fn test_accrual_window_starts_with_reward_period() {
+        let mut pool = Pool {
+            last_update_time: 100,
+            ..Pool::default()
+        };
+        assert_eq!(pool.accrual_window(150), (100, 150));
+
+        pool.reward_start_time = 120;
+        pool.reward_duration_end = 140;
+        assert_eq!(pool.accrual_window(110), (120, 120));
+        assert_eq!(pool.accrual_window(130), (120, 130));
+        // accrual goes on past the end of the period while the budget lasts
+        assert_eq!(pool.accrual_window(150), (120, 150));
+
+        pool.last_update_time = 160;
+        assert_eq!(pool.accrual_window(170), (160, 170));
+    }
+
+    #[test]
+    fn test_checkpoint_keeps_tier_window() {
+        let mut pool = Pool {
+            last_update_time: 100,
+            checkpoint_time: 100,
+            reward_duration_end: 200,
+            reward_a_rate_u128: 10 * PRECISION,
+            total_staked: 5,
+            ..Pool::default()
+        };
+        pool.checkpoint(150).unwrap();
+        assert_eq!(pool.reward_a_per_token_stored, 50 * 10 * PRECISION / 5);
+        assert_eq!(pool.checkpoint_time, 150);
+        assert_eq!(pool.last_update_time, 100);
+
+        // nothing accrues past the end of the reward period or twice
+        pool.checkpoint(300).unwrap();
+        pool.checkpoint(300).unwrap();
+        assert_eq!(pool.reward_a_per_token_stored, 100 * 10 * PRECISION / 5);
+        assert_eq!(pool.last_update_time, 100);
+    }
+
+    #[test]
+    fn test_accruable_rewards_capped_by_budget() {
+        let mut pool = Pool {
+            last_update_time: 0,
+            reward_start_time: 0,
+            reward_duration_end: 365,
+            total_reward: u64::MAX,
+            ..Pool::default()
+        };
+        assert_eq!(
+            pool.accruable_rewards(1_000),
+            Some(TOTAL_ANNUAL_REWARD * MAX_REWARD_MULTIPLIER)
+        );
+
+        pool.total_reward = 10;
+        assert_eq!(pool.accruable_rewards(1_000), Some(10));
+
+        // nothing is owed for the time past the end of the period
+        pool.last_update_time = 365;
+        assert_eq!(pool.accruable_rewards(1_000), Some(0));
+    }
 }
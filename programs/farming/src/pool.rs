@@ -11,6 +11,233 @@ fn calculate_reward_rate(funding_amount: u64, reward_duration: u64) -> Option<u1
     Some(reward_rate)
 }
 
+/// Emission rate in effect at `time` and the next timestamp at which it changes
+fn emission_rate_at(
+    segments: &[EmissionSegment],
+    base_rate: u128,
+    time: u64,
+) -> (u128, Option<u64>) {
+    let next_start = segments
+        .iter()
+        .map(|s| s.start_time)
+        .find(|start| *start > time);
+    let current = segments
+        .iter()
+        .rev()
+        .find(|s| s.start_time != 0 && s.start_time <= time);
+
+    match current {
+        None => (base_rate, next_start),
+        Some(segment) if segment.halving_interval == 0 => (segment.rate, next_start),
+        Some(segment) => {
+            let halvings = time
+                .saturating_sub(segment.start_time)
+                .checked_div(segment.halving_interval)
+                .unwrap_or_default();
+            let rate = u32::try_from(halvings)
+                .ok()
+                .and_then(|n| segment.rate.checked_shr(n))
+                .unwrap_or_default();
+            let next_halving = halvings
+                .checked_add(1)
+                .and_then(|n| n.checked_mul(segment.halving_interval))
+                .and_then(|offset| offset.checked_add(segment.start_time));
+            let next_change = match (next_start, next_halving) {
+                (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+                (a, b) => a.or(b),
+            };
+            if rate == 0 {
+                (rate, next_start)
+            } else {
+                (rate, next_change)
+            }
+        }
+    }
+}
+
+/// Integrate the emission schedule over `[from, to)`.
+/// Before the first segment starts the pool falls back to `base_rate`.
+/// Result is scaled by PRECISION, like the reward rate.
+pub fn integrate_emission(
+    segments: &[EmissionSegment],
+    base_rate: u128,
+    from: u64,
+    to: u64,
+) -> Option<u128> {
+    let segments: Vec<EmissionSegment> = segments
+        .iter()
+        .filter(|s| s.start_time != 0)
+        .copied()
+        .collect();
+    let mut total: u128 = 0;
+    let mut cursor = from;
+    while cursor < to {
+        let (rate, next_change) = emission_rate_at(&segments, base_rate, cursor);
+        let step_end = next_change.map_or(to, |t| std::cmp::min(t, to));
+        let elapsed: u128 = step_end.checked_sub(cursor)?.into();
+        total = total.checked_add(rate.checked_mul(elapsed)?)?;
+        cursor = step_end;
+    }
+    Some(total)
+}
+
+/// Reward owed to a stake of `balance_staked` over `[from, to)` under the utilization curve or the emission schedule
+pub fn scheduled_reward(
+    pool: &Pool,
+    balance_staked: u64,
+    total_staked: u64,
+    from: u64,
+    to: u64,
+) -> Option<u64> {
+    let emission = pool.emission_at_stake(from, to, total_staked)?;
+    U192::from(emission)
+        .checked_mul(balance_staked.into())?
+        .checked_div(total_staked.into())?
+        .checked_div(PRECISION.into())?
+        .try_into()
+        .ok()
+}
+
+/// Reward owed to a stake of `balance_staked` over `[from, to)`, before tier multipliers.
+/// Uses the utilization curve or the emission schedule when one is configured and the flat annual budget,
+/// in UI units of the reward token, otherwise.
+pub fn base_reward(
+    pool: &Pool,
+    balance_staked: u64,
+    total_staked: u64,
+    from: u64,
+    to: u64,
+) -> Option<u64> {
+    if pool.utilization_curve.is_set() || pool.has_emission_schedule() {
+        return scheduled_reward(pool, balance_staked, total_staked, from, to);
+    }
+    let seconds = to.checked_sub(from)?;
+    pool.reward_to_base_units(TOTAL_ANNUAL_REWARD.into())?
+        .checked_mul(balance_staked.into())?
+        .checked_mul(seconds.into())?
+        .checked_div(total_staked.into())?
+        .checked_div(365)?
+        .try_into()
+        .ok()
+}
+
+/// Converts `amount` in UI units of a mint with `decimals` to base units
+pub fn to_base_units(amount: u128, decimals: u8) -> Option<u128> {
+    10u128.checked_pow(decimals.into())?.checked_mul(amount)
+}
+
+/// Pool points per staked base unit accumulated up to `current_time`, scaled by PRECISION
+pub fn points_per_token_at(pool: &Pool, current_time: u64) -> Option<u128> {
+    let seconds = current_time.saturating_sub(pool.points_checkpoint_time);
+    if pool.points_per_token_second == 0 || seconds == 0 {
+        return Some(pool.points_per_token_stored);
+    }
+    // the rate is per staked token in UI units
+    let increment: u128 = U192::from(pool.points_per_token_second)
+        .checked_mul(seconds.into())?
+        .checked_div(pool.staking_to_base_units(1)?.into())?
+        .try_into()
+        .ok()?;
+    pool.points_per_token_stored.checked_add(increment)
+}
+
+/// Points of a user if it were updated at `current_time`
+pub fn user_points(pool: &Pool, user: &User, current_time: u64) -> Option<u128> {
+    let points_per_token = points_per_token_at(pool, current_time)?
+        .checked_sub(user.points_per_token_complete)?;
+    let accrued: u128 = U192::from(user.balance_staked)
+        .checked_mul(points_per_token.into())?
+        .checked_div(PRECISION.into())?
+        .try_into()
+        .ok()?;
+    user.points.checked_add(accrued)
+}
+
+/// Stake-seconds of `balance` held from `from` to `to` that fall within the epoch `window`
+pub fn stake_seconds_in(window: &EpochWindow, balance: u64, from: u64, to: u64) -> Option<u128> {
+    let start = std::cmp::max(from, window.start_time);
+    let end = std::cmp::min(to, window.end_time);
+    if window.epoch == 0 || start >= end {
+        return Some(0);
+    }
+    u128::from(balance).checked_mul(end.checked_sub(start)?.into())
+}
+
+/// Share of an epoch `budget` earned by `stake_seconds` out of the epoch `total_stake_seconds`
+pub fn epoch_reward(budget: u64, stake_seconds: u128, total_stake_seconds: u128) -> Option<u64> {
+    if total_stake_seconds == 0 {
+        return Some(0);
+    }
+    U192::from(budget)
+        .checked_mul(stake_seconds.into())?
+        .checked_div(total_stake_seconds.into())?
+        .try_into()
+        .ok()
+}
+
+/// Tier multiplier for rewards accrued over `seconds` since the last pool update
+pub fn reward_multiplier(seconds: u64) -> u64 {
+    match seconds {
+        0 => 0,
+        1..=3 => 1,
+        4..=7 => 2,
+        8..=15 => 3,
+        _ => MAX_REWARD_MULTIPLIER,
+    }
+}
+
+/// Reward a stake of `balance_staked` earns if rewards are updated at `current_time`,
+/// with the tier multiplier applied. Capped by the remaining budget, nothing is mutated.
+/// Returns the multiplier and the reward.
+pub fn accrued_reward(
+    pool: &Pool,
+    balance_staked: u64,
+    total_staked: u64,
+    current_time: u64,
+) -> Option<(u64, u64)> {
+    let (accrual_start, accrual_end) = pool.accrual_window(current_time);
+    let mut seconds = accrual_end.checked_sub(accrual_start)?;
+    if seconds < 1 && accrual_end == current_time && current_time >= pool.reward_start_time {
+        seconds = 1;
+    }
+    let total_staked = std::cmp::max(total_staked, 1);
+    let window_end = accrual_start.checked_add(seconds)?;
+    let reward_unit = base_reward(pool, balance_staked, total_staked, accrual_start, window_end)?;
+    let multiplier = reward_multiplier(seconds);
+    let reward = reward_unit.checked_mul(multiplier)?;
+    Some((multiplier, std::cmp::min(reward, pool.total_reward)))
+}
+
+/// Like [accrued_reward] for a user, leaving out the time before its latest deposits mature.
+/// Returns the multiplier and the reward.
+pub fn user_accrued_reward(
+    pool: &Pool,
+    user: &User,
+    total_staked: u64,
+    current_time: u64,
+) -> Option<(u64, u64)> {
+    let immature = std::cmp::min(user.immature_balance, user.balance_staked);
+    let mature = user.balance_staked.checked_sub(immature)?;
+    let (multiplier, mut reward) = accrued_reward(pool, mature, total_staked, current_time)?;
+    if immature > 0 && user.matures_at <= current_time {
+        let (_, immature_reward) = accrued_reward(pool, immature, total_staked, current_time)?;
+        let (accrual_start, accrual_end) = pool.accrual_window(current_time);
+        let window = accrual_end.saturating_sub(accrual_start);
+        let matured_share = if user.matures_at <= accrual_start || window == 0 {
+            immature_reward
+        } else {
+            let matured = accrual_end.saturating_sub(user.matures_at);
+            u128::from(immature_reward)
+                .checked_mul(matured.into())?
+                .checked_div(window.into())?
+                .try_into()
+                .ok()?
+        };
+        reward = reward.checked_add(matured_share)?;
+    }
+    Some((multiplier, std::cmp::min(reward, pool.total_reward)))
+}
+
 /// Calculate reward per token
 pub fn reward_per_token(
     pool: &Account<Pool>,
@@ -26,15 +253,15 @@ pub fn reward_per_token(
         //);
     }
 
-    let time_period = U192::from(last_time_reward_applicable)
-        .checked_sub(pool.last_update_time.into())
-        .unwrap();
+    let accrual_start = std::cmp::max(pool.last_update_time, pool.reward_start_time);
+    let emission = U192::from(
+        pool.emission_at_stake(accrual_start, last_time_reward_applicable, total_staked)
+            .unwrap(),
+    );
     let a = pool
         .reward_a_per_token_stored
         .checked_add(
-            time_period
-                .checked_mul(pool.get_reward_a_rate().into())
-                .unwrap()
+            emission
                 .checked_div(total_staked.into())
                 .unwrap()
                 .try_into()
@@ -74,12 +301,15 @@ pub fn rate_after_funding(
     let reward_period_end = pool.reward_duration_end;
 
     if current_time >= reward_period_end {
-        let a = calculate_reward_rate(funding_amount_a, pool.reward_duration).unwrap();
+        let a = calculate_reward_rate(funding_amount_a, pool.period_duration()).unwrap();
         // let b = calculate_reward_rate(funding_amount_b, pool.reward_duration).unwrap();
         // Ok((a, b))
         Ok(a)
     } else {
-        let remaining_seconds = reward_period_end.checked_sub(current_time).unwrap();
+        // a period that has not started yet still holds its whole budget
+        let remaining_seconds = reward_period_end
+            .checked_sub(std::cmp::max(current_time, pool.reward_start_time))
+            .unwrap();
         let leftover_a: u64 = (remaining_seconds as u128)
             .checked_mul(pool.get_reward_a_rate())
             .unwrap()
@@ -98,7 +328,7 @@ pub fn rate_after_funding(
         let total_a = leftover_a.checked_add(funding_amount_a).unwrap();
         // let total_b = leftover_b.checked_add(funding_amount_b).unwrap();
 
-        let a = calculate_reward_rate(total_a, pool.reward_duration).unwrap();
+        let a = calculate_reward_rate(total_a, pool.period_duration()).unwrap();
         // let b = calculate_reward_rate(total_b, pool.reward_duration).unwrap();
         Ok(a)
     }
@@ -139,6 +369,243 @@ pub fn user_earned_amount(pool: &Account<Pool>, user: &Account<User>) -> u64 {
     a
 }
 
+#[cfg(test)]
+mod emission_test {
+    use super::*;
+
+    fn segment(start_time: u64, rate: u128, halving_interval: u64) -> EmissionSegment {
+        EmissionSegment {
+            start_time,
+            rate,
+            halving_interval,
+        }
+    }
+
+    #[test]
+    fn test_accrued_reward_tiers() {
+        let pool = Pool {
+            last_update_time: 0,
+            total_reward: u64::MAX,
+            ..Pool::default()
+        };
+        let unit = |seconds: u64| base_reward(&pool, 1, 1, 0, seconds).unwrap();
+        assert_eq!(accrued_reward(&pool, 1, 1, 2), Some((1, unit(2))));
+        assert_eq!(accrued_reward(&pool, 1, 1, 5), Some((2, unit(5) * 2)));
+        assert_eq!(accrued_reward(&pool, 1, 1, 8), Some((3, unit(8) * 3)));
+        assert_eq!(accrued_reward(&pool, 1, 1, 100), Some((4, unit(100) * 4)));
+
+        // same-second updates still accrue one second
+        assert_eq!(accrued_reward(&pool, 1, 1, 0), Some((1, unit(1))));
+
+        let capped = Pool {
+            total_reward: 3,
+            ..pool
+        };
+        assert_eq!(accrued_reward(&capped, 1, 1, 100), Some((4, 3)));
+    }
+
+    #[test]
+    fn test_accrued_reward_after_reward_period_start() {
+        let mut pool = Pool {
+            last_update_time: 40,
+            reward_start_time: 20,
+            reward_duration_end: 50,
+            total_reward: u64::MAX,
+            ..Pool::default()
+        };
+        let unit = |seconds: u64| base_reward(&pool, 1, 1, 40, 40 + seconds).unwrap();
+        // accrual goes on past the end of the period while the budget lasts
+        assert_eq!(
+            accrued_reward(&pool, 1, 1, 100),
+            Some((reward_multiplier(60), unit(60) * reward_multiplier(60)))
+        );
+        let capped = Pool {
+            total_reward: 3,
+            ..pool
+        };
+        assert_eq!(accrued_reward(&capped, 1, 1, 100), Some((4, 3)));
+
+        // no minimum second before the period starts
+        pool.last_update_time = 0;
+        pool.reward_start_time = 200;
+        pool.reward_duration_end = 300;
+        assert_eq!(accrued_reward(&pool, 1, 1, 150), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_immature_stake_accrues_after_maturity() {
+        let pool = Pool {
+            last_update_time: 0,
+            total_reward: u64::MAX,
+            ..Pool::default()
+        };
+        let user = User {
+            balance_staked: 4,
+            immature_balance: 3,
+            matures_at: 60,
+            ..User::default()
+        };
+        let full = |balance: u64| accrued_reward(&pool, balance, 4, 100).unwrap().1;
+
+        // only the mature part earns before maturity
+        assert_eq!(
+            user_accrued_reward(&pool, &user, 4, 50).unwrap().1,
+            accrued_reward(&pool, 1, 4, 50).unwrap().1
+        );
+        // the immature part earns from maturity on
+        assert_eq!(
+            user_accrued_reward(&pool, &user, 4, 100).unwrap().1,
+            full(1) + full(3) * 40 / 100
+        );
+        let matured = User {
+            matures_at: 0,
+            ..user
+        };
+        assert_eq!(
+            user_accrued_reward(&pool, &matured, 4, 100).unwrap().1,
+            full(1) + full(3)
+        );
+    }
+
+    #[test]
+    fn test_utilization_curve_interpolation() {
+        let mut curve = UtilizationCurve {
+            len: 3,
+            ..UtilizationCurve::default()
+        };
+        curve.points[0] = CurvePoint { total_staked: 100, rate: 10 };
+        curve.points[1] = CurvePoint { total_staked: 200, rate: 30 };
+        curve.points[2] = CurvePoint { total_staked: 400, rate: 20 };
+        assert!(curve.is_valid());
+        assert_eq!(curve.rate_at(0), Some(10));
+        assert_eq!(curve.rate_at(150), Some(20));
+        assert_eq!(curve.rate_at(200), Some(30));
+        // falling past the kink
+        assert_eq!(curve.rate_at(300), Some(25));
+        assert_eq!(curve.rate_at(u64::MAX), Some(20));
+        assert_eq!(UtilizationCurve::default().rate_at(100), None);
+
+        // the curve replaces the annual budget, shared pro rata
+        let pool = Pool {
+            utilization_curve: UtilizationCurve {
+                points: [
+                    CurvePoint { total_staked: 0, rate: 4 * PRECISION },
+                    CurvePoint::default(),
+                    CurvePoint::default(),
+                    CurvePoint::default(),
+                ],
+                len: 1,
+            },
+            ..Pool::default()
+        };
+        assert_eq!(base_reward(&pool, 1, 4, 0, 10), Some(10));
+
+        curve.points[3] = CurvePoint { total_staked: 500, rate: 1 };
+        assert!(!curve.is_valid());
+        curve.len = 4;
+        assert!(curve.is_valid());
+        curve.points[3].total_staked = 400;
+        assert!(!curve.is_valid());
+    }
+
+    #[test]
+    fn test_annual_budget_in_ui_units() {
+        let legacy = Pool::default();
+        let pool = Pool {
+            reward_decimals: 9,
+            ..Pool::default()
+        };
+        assert_eq!(base_reward(&legacy, 1, 2, 0, 365), Some(TOTAL_ANNUAL_REWARD / 2));
+        assert_eq!(
+            base_reward(&pool, 1, 2, 0, 365),
+            Some(TOTAL_ANNUAL_REWARD * 1_000_000_000 / 2)
+        );
+        // large stakes no longer overflow the intermediate product
+        assert!(base_reward(&pool, u64::MAX / 2, u64::MAX, 0, 365).is_some());
+        assert_eq!(to_base_units(15, 6), Some(15_000_000));
+        assert_eq!(to_base_units(1, 39), None);
+    }
+
+    #[test]
+    fn test_points_accrue_per_token_second() {
+        let mut pool = Pool {
+            staking_decimals: 6,
+            points_per_token_second: 2 * PRECISION,
+            points_checkpoint_time: 100,
+            ..Pool::default()
+        };
+        let user = User {
+            balance_staked: 5_000_000,
+            ..User::default()
+        };
+        // 5 tokens for 10 seconds at 2 points each
+        assert_eq!(user_points(&pool, &user, 110), Some(100));
+
+        // a rate change only applies from the checkpoint on
+        pool.checkpoint_points(110).unwrap();
+        pool.points_per_token_second = 0;
+        assert_eq!(user_points(&pool, &user, 200), Some(100));
+
+        let updated = User {
+            points: 100,
+            points_per_token_complete: pool.points_per_token_stored,
+            ..user
+        };
+        assert_eq!(user_points(&pool, &updated, 200), Some(100));
+    }
+
+    #[test]
+    fn test_epoch_reward_by_stake_seconds() {
+        let window = EpochWindow {
+            epoch: 1,
+            start_time: 100,
+            end_time: 200,
+        };
+        // only the part of the holding within the epoch counts
+        assert_eq!(stake_seconds_in(&window, 10, 50, 150), Some(500));
+        assert_eq!(stake_seconds_in(&window, 10, 200, 300), Some(0));
+        assert_eq!(stake_seconds_in(&EpochWindow::default(), 10, 0, 300), Some(0));
+
+        assert_eq!(epoch_reward(1_000, 500, 1_500), Some(333));
+        assert_eq!(epoch_reward(u64::MAX, u128::MAX, u128::MAX), Some(u64::MAX));
+        assert_eq!(epoch_reward(1_000, 0, 0), Some(0));
+    }
+
+    #[test]
+    fn test_base_rate_without_segments() {
+        let segments = [EmissionSegment::default(); MAX_EMISSION_SEGMENTS];
+        assert_eq!(integrate_emission(&segments, 7, 100, 110), Some(70));
+        assert_eq!(integrate_emission(&segments, 7, 110, 100), Some(0));
+    }
+
+    #[test]
+    fn test_integrate_across_segment_boundaries() {
+        let mut segments = [EmissionSegment::default(); MAX_EMISSION_SEGMENTS];
+        segments[0] = segment(100, 10, 0);
+        segments[1] = segment(200, 4, 0);
+        // 50s of base rate, 100s at 10, 50s at 4
+        assert_eq!(integrate_emission(&segments, 1, 50, 250), Some(50 + 1000 + 200));
+        // window fully inside one segment
+        assert_eq!(integrate_emission(&segments, 1, 120, 130), Some(100));
+    }
+
+    #[test]
+    fn test_halving_within_segment() {
+        let mut segments = [EmissionSegment::default(); MAX_EMISSION_SEGMENTS];
+        segments[0] = segment(100, 16, 10);
+        // 10s at 16, 10s at 8, 5s at 4
+        assert_eq!(integrate_emission(&segments, 0, 100, 125), Some(160 + 80 + 20));
+        // halving stops when the next segment takes over
+        segments[1] = segment(115, 3, 0);
+        assert_eq!(integrate_emission(&segments, 0, 100, 125), Some(160 + 40 + 30));
+        // rate eventually halves down to zero
+        assert_eq!(
+            integrate_emission(&[segment(1, 1, 1)], 0, 1, u64::MAX),
+            Some(1)
+        );
+    }
+}
+
 #[cfg(test)]
 mod overflow_test {
     use super::*;
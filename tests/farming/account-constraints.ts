@@ -0,0 +1,289 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  createTestUser,
+  expectError,
+  getPoolIndexPda,
+  getPoolMetadataPda,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const OTHER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("token account constraints", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let adminStakingATA: anchor.web3.PublicKey = null;
+  let adminRewardATA: anchor.web3.PublicKey = null;
+  let otherStakingATA: anchor.web3.PublicKey = null;
+  let otherRewardATA: anchor.web3.PublicKey = null;
+
+  const deposit = (stakeFromAccount: anchor.web3.PublicKey) =>
+    program.methods
+      .deposit(new anchor.BN(10 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const claim = (rewardAAccount: anchor.web3.PublicKey) =>
+    program.methods
+      .claim()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const claimMany = (rewardAAccount: anchor.web3.PublicKey) =>
+    program.methods
+      .claimMany()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        [farmingPoolAddress, userStakingAddress, rewardAVault, rewardAAccount].map(
+          (pubkey) => ({ pubkey, isSigner: false, isWritable: true })
+        )
+      )
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const fund = (fromA: anchor.web3.PublicKey) =>
+    program.methods
+      .fund(new anchor.BN(TOKEN_MULTIPLIER), null)
+      .accounts({
+        fromA,
+        funder: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const withdrawExtraToken = (withdrawToAccount: anchor.web3.PublicKey) =>
+    program.methods
+      .withdrawExtraToken()
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        // optional accounts left out
+        queuedAction: program.programId,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        withdrawToAccount,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const closePool = async (
+    refundee: anchor.web3.PublicKey,
+    stakingRefundee: anchor.web3.PublicKey,
+    rewardARefundee: anchor.web3.PublicKey
+  ) => {
+    const [poolIndex] = await getPoolIndexPda(program, stakingToken.publicKey);
+    const [poolMetadata] = await getPoolMetadataPda(program, farmingPoolAddress);
+    return program.methods
+      .closePool()
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        poolIndex,
+        poolMetadata,
+        // optional account left out
+        queuedAction: program.programId,
+        refundee,
+        rewardARefundee,
+        rewardAVault,
+        stakingRefundee,
+        stakingVault,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+  };
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+    await airdrop(program, OTHER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    adminStakingATA = await stakingToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    adminRewardATA = await rewardToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    otherStakingATA = await stakingToken.createAssociatedTokenAccount(
+      OTHER_KEYPAIR.publicKey
+    );
+    otherRewardATA = await rewardToken.createAssociatedTokenAccount(
+      OTHER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      otherStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      100 * TOKEN_MULTIPLIER
+    );
+    await rewardToken.mintTo(
+      userRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      100 * TOKEN_MULTIPLIER
+    );
+    await rewardToken.mintTo(
+      otherRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      100 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should reject deposits from a token account of another mint", async () => {
+    await assert.rejects(
+      deposit(userRewardATA),
+      expectError("InvalidStakingMint")
+    );
+  });
+
+  it("should reject deposits from a token account of another wallet", async () => {
+    await assert.rejects(
+      deposit(otherStakingATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+  });
+
+  it("should reject claims to a token account of another mint", async () => {
+    await assert.rejects(claim(userStakingATA), expectError("InvalidRewardMint"));
+    await assert.rejects(
+      claimMany(userStakingATA),
+      expectError("InvalidRewardMint")
+    );
+  });
+
+  it("should reject claims to a token account of another wallet", async () => {
+    await assert.rejects(
+      claim(otherRewardATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+    await assert.rejects(
+      claimMany(otherRewardATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+  });
+
+  it("should reject funding from a token account of another mint", async () => {
+    await assert.rejects(fund(adminStakingATA), expectError("InvalidRewardMint"));
+  });
+
+  it("should reject funding from a token account of another wallet", async () => {
+    await assert.rejects(
+      fund(otherRewardATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+  });
+
+  it("should reject extra token withdrawals to a token account of another mint", async () => {
+    await assert.rejects(
+      withdrawExtraToken(adminRewardATA),
+      expectError("InvalidStakingMint")
+    );
+  });
+
+  it("should reject extra token withdrawals to a token account of another wallet", async () => {
+    await assert.rejects(
+      withdrawExtraToken(otherStakingATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+  });
+
+  it("should reject closing the pool to a refundee other than the authority", async () => {
+    await assert.rejects(
+      closePool(OTHER_KEYPAIR.publicKey, adminStakingATA, adminRewardATA),
+      expectError("InvalidRefundee")
+    );
+  });
+
+  it("should reject closing the pool to refundee token accounts of another mint", async () => {
+    await assert.rejects(
+      closePool(ADMIN_KEYPAIR.publicKey, adminRewardATA, adminRewardATA),
+      expectError("InvalidStakingMint")
+    );
+    await assert.rejects(
+      closePool(ADMIN_KEYPAIR.publicKey, adminStakingATA, adminStakingATA),
+      expectError("InvalidRewardMint")
+    );
+  });
+
+  it("should reject closing the pool to refundee token accounts of another wallet", async () => {
+    await assert.rejects(
+      closePool(ADMIN_KEYPAIR.publicKey, otherStakingATA, adminRewardATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+    await assert.rejects(
+      closePool(ADMIN_KEYPAIR.publicKey, adminStakingATA, otherRewardATA),
+      expectError("InvalidTokenAccountOwner")
+    );
+  });
+});
@@ -0,0 +1,93 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, expectEvent } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const KEEPER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+const PRECISION = new anchor.BN(1_000_000_000);
+
+describe("permissionless checkpoint", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+
+  const checkpoint = () =>
+    expectEvent<any>(program, "EventCheckpoint", () =>
+      program.methods
+        .checkpoint()
+        .accounts({
+          caller: KEEPER_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+        })
+        .signers([KEEPER_KEYPAIR])
+        .rpc()
+    );
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, KEEPER_KEYPAIR.publicKey);
+    ({ pool: farmingPoolAddress } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    await program.methods
+      .setPointsRate(PRECISION, true)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+  });
+
+  it("should advance the reward and points accounting without touching the tier window", async () => {
+    const before = await program.account.pool.fetch(farmingPoolAddress);
+
+    const first = await checkpoint();
+    let pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(
+      pool.pointsCheckpointTime.toNumber(),
+      first.timestamp.toNumber()
+    );
+    assert.ok(pool.pointsPerTokenStored.eq(first.pointsPerTokenStored));
+    assert.strictEqual(
+      pool.checkpointTime.toNumber(),
+      first.timestamp.toNumber()
+    );
+    assert.ok(pool.rewardAPerTokenStored.eq(first.rewardAPerTokenStored));
+
+    const second = await checkpoint();
+    pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(
+      pool.pointsCheckpointTime.toNumber(),
+      second.timestamp.toNumber()
+    );
+    assert.strictEqual(
+      pool.checkpointTime.toNumber(),
+      second.timestamp.toNumber()
+    );
+    // one point per staked token per second between the two on-chain timestamps
+    const elapsed = second.timestamp.toNumber() - first.timestamp.toNumber();
+    assert.ok(
+      second.pointsPerTokenStored
+        .sub(first.pointsPerTokenStored)
+        .eq(PRECISION.muln(elapsed).divn(TOKEN_MULTIPLIER))
+    );
+
+    assert.strictEqual(
+      pool.lastUpdateTime.toString(),
+      before.lastUpdateTime.toString()
+    );
+  });
+});
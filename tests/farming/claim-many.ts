@@ -0,0 +1,131 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  chargeTestPool,
+  createTestPool,
+  createTestUser,
+  sleep,
+  TestPool,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("claim from several pools at once", () => {
+  const pools: TestPool[] = [];
+  const users: anchor.web3.PublicKey[] = [];
+  const rewardATAs: anchor.web3.PublicKey[] = [];
+
+  const remainingAccounts = (count: number) =>
+    pools
+      .flatMap((pool, i) => [
+        pool.pool,
+        users[i],
+        pool.rewardAVault,
+        rewardATAs[i],
+      ])
+      .slice(0, count)
+      .map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }));
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    for (let i = 0; i < 2; i++) {
+      const pool = await createTestPool(
+        program,
+        ADMIN_KEYPAIR,
+        anchor.web3.Keypair.generate(),
+        TOKEN_DECIMAL
+      );
+      pools.push(pool);
+      const user = await createTestUser(program, pool.pool, USER_KEYPAIR);
+      users.push(user);
+
+      const stakingATA = await pool.stakingToken.createAssociatedTokenAccount(
+        USER_KEYPAIR.publicKey
+      );
+      rewardATAs.push(
+        await pool.rewardToken.createAssociatedTokenAccount(
+          USER_KEYPAIR.publicKey
+        )
+      );
+      await pool.stakingToken.mintTo(
+        stakingATA,
+        ADMIN_KEYPAIR,
+        [],
+        100 * TOKEN_MULTIPLIER
+      );
+      await chargeTestPool(
+        program,
+        ADMIN_KEYPAIR,
+        pool,
+        1_000_000 * TOKEN_MULTIPLIER
+      );
+      await program.methods
+        .deposit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+        .accounts({
+          owner: USER_KEYPAIR.publicKey,
+          pool: pool.pool,
+          stakeFromAccount: stakingATA,
+          stakingVault: pool.stakingVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          user,
+        })
+        .signers([USER_KEYPAIR])
+        .rpc();
+    }
+  });
+
+  it("should reject incomplete account groups", async () => {
+    await assert.rejects(
+      program.methods
+        .claimMany()
+        .accounts({
+          owner: USER_KEYPAIR.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts(3))
+        .signers([USER_KEYPAIR])
+        .rpc(),
+      (err: anchor.AnchorError) => {
+        assert.strictEqual(
+          err.error.errorCode.code,
+          "InvalidRemainingAccounts"
+        );
+        return true;
+      }
+    );
+  });
+
+  it("should claim every pool in one instruction", async () => {
+    await sleep(2000);
+    await program.methods
+      .claimMany()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts(8))
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+    for (const rewardATA of rewardATAs) {
+      const balance = await provider.connection.getTokenAccountBalance(
+        rewardATA
+      );
+      assert.ok(Number(balance.value.amount) > 0);
+    }
+  });
+});
@@ -0,0 +1,39 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 9;
+
+describe("mint decimals", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    ({ pool: farmingPoolAddress } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+  });
+
+  it("should record the mint decimals at init", async () => {
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.stakingDecimals, TOKEN_DECIMAL);
+    assert.strictEqual(pool.rewardDecimals, TOKEN_DECIMAL);
+  });
+
+  it("should start with an empty reward budget", async () => {
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.totalReward.toNumber(), 0);
+  });
+});
@@ -0,0 +1,233 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { LAMPORTS_PER_SOL } from "@solana/web3.js";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { getClock } from "../clock";
+import {
+  ensureGlobalConfig,
+  getPoolIndexPda,
+  getPoolPda,
+  getRewardAVaultPda,
+  getStakingVaultPda,
+  getUserPda,
+  REWARD_DURATION,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+const START_DELAY = 30;
+
+describe("farming with a delayed reward start", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let adminRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  before(async () => {
+    for (const keypair of [ADMIN_KEYPAIR, USER_KEYPAIR]) {
+      const sig = await program.provider.connection.requestAirdrop(
+        keypair.publicKey,
+        100 * LAMPORTS_PER_SOL
+      );
+      await program.provider.connection.confirmTransaction(sig);
+    }
+
+    stakingToken = await Token.createMint(
+      program.provider.connection,
+      ADMIN_KEYPAIR,
+      ADMIN_KEYPAIR.publicKey,
+      null,
+      TOKEN_DECIMAL,
+      TOKEN_PROGRAM_ID
+    );
+    rewardToken = await Token.createMint(
+      program.provider.connection,
+      ADMIN_KEYPAIR,
+      ADMIN_KEYPAIR.publicKey,
+      null,
+      TOKEN_DECIMAL,
+      TOKEN_PROGRAM_ID
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    adminRewardATA = await rewardToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await rewardToken.mintTo(
+      adminRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+
+    [farmingPoolAddress] = await getPoolPda(
+      program,
+      stakingToken.publicKey,
+      rewardToken.publicKey,
+      BASE_KEYPAIR.publicKey
+    );
+    [userStakingAddress] = await getUserPda(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR.publicKey
+    );
+    const [stakingVaultAddress] = await getStakingVaultPda(
+      program,
+      stakingToken.publicKey,
+      rewardToken.publicKey,
+      BASE_KEYPAIR.publicKey
+    );
+    const [rewardAVaultAddress] = await getRewardAVaultPda(
+      program,
+      stakingToken.publicKey,
+      rewardToken.publicKey,
+      BASE_KEYPAIR.publicKey
+    );
+
+    const globalConfig = await ensureGlobalConfig(program);
+    const [poolIndex] = await getPoolIndexPda(program, stakingToken.publicKey);
+    await program.methods
+      .initializePool(REWARD_DURATION)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        base: BASE_KEYPAIR.publicKey,
+        globalConfig,
+        pool: farmingPoolAddress,
+        poolIndex,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        rewardAMint: rewardToken.publicKey,
+        rewardAVault: rewardAVaultAddress,
+        stakingMint: stakingToken.publicKey,
+        stakingVault: stakingVaultAddress,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([BASE_KEYPAIR, ADMIN_KEYPAIR])
+      .rpc();
+
+    await program.methods
+      .createUser()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+  });
+
+  it("should reject a start time in the past", async () => {
+    const clock = await getClock(program.provider.connection);
+    const poolAccount = await program.account.pool.fetch(farmingPoolAddress);
+
+    await assert.rejects(
+      program.methods
+        .fund(
+          new anchor.BN(TOKEN_MULTIPLIER),
+          new anchor.BN(clock.info.unixTimestamp - 100)
+        )
+        .accounts({
+          fromA: adminRewardATA,
+          funder: ADMIN_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+          rewardAVault: poolAccount.rewardAVault,
+          stakingVault: poolAccount.stakingVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([ADMIN_KEYPAIR])
+        .rpc(),
+      (err: anchor.AnchorError) => {
+        assert.strictEqual(err.error.errorCode.code, "RewardStartTimeInPast");
+        return true;
+      }
+    );
+  });
+
+  it("should pre-fund the pool with a future start time", async () => {
+    const clock = await getClock(program.provider.connection);
+    const startTime = new anchor.BN(clock.info.unixTimestamp + START_DELAY);
+    const poolAccount = await program.account.pool.fetch(farmingPoolAddress);
+
+    await program.methods
+      .fund(new anchor.BN(10 * TOKEN_MULTIPLIER), startTime)
+      .accounts({
+        fromA: adminRewardATA,
+        funder: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault: poolAccount.rewardAVault,
+        stakingVault: poolAccount.stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+    const fundedPool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(
+      fundedPool.rewardStartTime.toString(),
+      startTime.toString()
+    );
+    assert.strictEqual(
+      fundedPool.rewardDurationEnd.toString(),
+      startTime.add(REWARD_DURATION).toString()
+    );
+  });
+
+  it("should accept deposits but accrue nothing before the start", async () => {
+    const poolAccount = await program.account.pool.fetch(farmingPoolAddress);
+
+    await program.methods
+      .deposit(new anchor.BN(500 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault: poolAccount.stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+    await program.methods
+      .claim()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount: userRewardATA,
+        rewardAVault: poolAccount.rewardAVault,
+        stakingVault: poolAccount.stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+    const userRewardBalance =
+      await provider.connection.getTokenAccountBalance(userRewardATA);
+    assert.strictEqual(userRewardBalance.value.amount, "0");
+  });
+});
@@ -0,0 +1,214 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  createTestUser,
+  expectError,
+  expectEvent,
+  getUserPda,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const PAYER_KEYPAIR = anchor.web3.Keypair.generate();
+const BENEFICIARY_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("deposit on behalf of a beneficiary", () => {
+  let stakingToken: Token = null;
+  let payerStakingATA: anchor.web3.PublicKey = null;
+  let beneficiaryStakingATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let beneficiaryStakingAddress: anchor.web3.PublicKey = null;
+  let payerStakingAddress: anchor.web3.PublicKey = null;
+
+  const depositFor = (amount: number) =>
+    program.methods
+      .depositFor(new anchor.BN(amount * TOKEN_MULTIPLIER))
+      .accounts({
+        beneficiary: BENEFICIARY_KEYPAIR.publicKey,
+        payer: PAYER_KEYPAIR.publicKey,
+        payerUser: payerStakingAddress,
+        pool: farmingPoolAddress,
+        stakeFromAccount: payerStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        user: beneficiaryStakingAddress,
+      })
+      .signers([PAYER_KEYPAIR])
+      .rpc();
+
+  const withdraw = (
+    owner: anchor.web3.Keypair,
+    stakeFromAccount: anchor.web3.PublicKey
+  ) =>
+    program.methods
+      .withdraw(new anchor.BN(10 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: owner.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakeFromAccount,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: beneficiaryStakingAddress,
+      })
+      .signers([owner])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, PAYER_KEYPAIR.publicKey);
+    await airdrop(program, BENEFICIARY_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    [beneficiaryStakingAddress] = await getUserPda(
+      program,
+      farmingPoolAddress,
+      BENEFICIARY_KEYPAIR.publicKey
+    );
+    [payerStakingAddress] = await getUserPda(
+      program,
+      farmingPoolAddress,
+      PAYER_KEYPAIR.publicKey
+    );
+    payerStakingATA = await stakingToken.createAssociatedTokenAccount(
+      PAYER_KEYPAIR.publicKey
+    );
+    beneficiaryStakingATA = await stakingToken.createAssociatedTokenAccount(
+      BENEFICIARY_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      payerStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should credit the beneficiary and record both wallets", async () => {
+    const event = await expectEvent<any>(program, "EventDeposit", () =>
+      depositFor(100)
+    );
+    assert.deepStrictEqual(event.payer, PAYER_KEYPAIR.publicKey);
+    assert.deepStrictEqual(event.owner, BENEFICIARY_KEYPAIR.publicKey);
+    assert.strictEqual(event.amount.toNumber(), 100 * TOKEN_MULTIPLIER);
+
+    const user = await program.account.user.fetch(beneficiaryStakingAddress);
+    assert.deepStrictEqual(user.owner, BENEFICIARY_KEYPAIR.publicKey);
+    assert.strictEqual(
+      user.balanceStaked.toNumber(),
+      100 * TOKEN_MULTIPLIER
+    );
+
+    const payerAccount = await stakingToken.getAccountInfo(payerStakingATA);
+    assert.strictEqual(payerAccount.amount.toNumber(), 900 * TOKEN_MULTIPLIER);
+  });
+
+  it("should add to an existing beneficiary account", async () => {
+    await depositFor(50);
+
+    const user = await program.account.user.fetch(beneficiaryStakingAddress);
+    assert.strictEqual(
+      user.balanceStaked.toNumber(),
+      150 * TOKEN_MULTIPLIER
+    );
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.userStakeCount.toNumber(), 1);
+  });
+
+  it("should only let the beneficiary withdraw", async () => {
+    await assert.rejects(withdraw(PAYER_KEYPAIR, payerStakingATA));
+
+    await withdraw(BENEFICIARY_KEYPAIR, beneficiaryStakingATA);
+    const beneficiaryAccount = await stakingToken.getAccountInfo(
+      beneficiaryStakingATA
+    );
+    assert.strictEqual(
+      beneficiaryAccount.amount.toNumber(),
+      10 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should reject a frozen payer", async () => {
+    const setFrozen = (frozen: boolean) =>
+      (frozen ? program.methods.freezeUser() : program.methods.unfreezeUser())
+        .accounts({
+          authority: ADMIN_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+          user: payerStakingAddress,
+        })
+        .signers([ADMIN_KEYPAIR])
+        .rpc();
+
+    await createTestUser(program, farmingPoolAddress, PAYER_KEYPAIR);
+    await setFrozen(true);
+    await assert.rejects(depositFor(10), expectError("UserFrozen"));
+
+    await setFrozen(false);
+    await depositFor(10);
+  });
+
+  it("should not restart the beneficiary's min stake window", async () => {
+    await program.methods
+      .setMinStakeSeconds(new anchor.BN(3600))
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+    await program.methods
+      .deposit(new anchor.BN(5 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: BENEFICIARY_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: beneficiaryStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: beneficiaryStakingAddress,
+      })
+      .signers([BENEFICIARY_KEYPAIR])
+      .rpc();
+    const before = await program.account.user.fetch(beneficiaryStakingAddress);
+
+    await sleep(2000);
+    await depositFor(10);
+
+    const user = await program.account.user.fetch(beneficiaryStakingAddress);
+    assert.strictEqual(user.maturesAt.toString(), before.maturesAt.toString());
+    assert.strictEqual(
+      user.lastDepositTime.toString(),
+      before.lastDepositTime.toString()
+    );
+    assert.strictEqual(
+      user.immatureBalance.toNumber(),
+      15 * TOKEN_MULTIPLIER
+    );
+  });
+});
@@ -0,0 +1,208 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, expectError, getUserPda } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+const PAUSE_DEPOSIT = 1 << 0;
+const USER_VERSION = 7;
+const USER_SIZE = 8 + 359;
+
+// keys of the pool and owner of the baseline user account loaded from fixtures/legacy-user.json
+const fixedKeypair = (seed: number) =>
+  anchor.web3.Keypair.fromSeed(new Uint8Array(32).fill(seed));
+const LEGACY_STAKING_MINT = fixedKeypair(1);
+const LEGACY_REWARD_MINT = fixedKeypair(2);
+const LEGACY_BASE = fixedKeypair(3);
+const LEGACY_OWNER = fixedKeypair(4);
+const LEGACY_USER = new anchor.web3.PublicKey(
+  "Dfv4frEpiuNUawma1HQo1t4BsnmUyrc3bbNPGgCv4NyP"
+);
+
+describe("deposit with user account creation", () => {
+  let stakingToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const depositWithInit = (amount: number) =>
+    program.methods
+      .depositWithInit(new anchor.BN(amount * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    [userStakingAddress] = await getUserPda(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR.publicKey
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should reject the first deposit while deposits are paused", async () => {
+    await program.methods
+      .setPauseFlags(PAUSE_DEPOSIT)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+    await assert.rejects(depositWithInit(100), expectError("DepositPaused"));
+    const account = await provider.connection.getAccountInfo(
+      userStakingAddress
+    );
+    assert.strictEqual(account, null);
+
+    await program.methods
+      .setPauseFlags(0)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+  });
+
+  it("should create the user account on the first deposit", async () => {
+    await depositWithInit(100);
+
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.deepStrictEqual(user.owner, USER_KEYPAIR.publicKey);
+    assert.deepStrictEqual(user.pool, farmingPoolAddress);
+    assert.strictEqual(
+      user.balanceStaked.toNumber(),
+      100 * TOKEN_MULTIPLIER
+    );
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.userStakeCount.toNumber(), 1);
+    assert.strictEqual(pool.totalStaked.toNumber(), 100 * TOKEN_MULTIPLIER);
+  });
+
+  it("should reuse the existing user account on later deposits", async () => {
+    await depositWithInit(50);
+
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(
+      user.balanceStaked.toNumber(),
+      150 * TOKEN_MULTIPLIER
+    );
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.userStakeCount.toNumber(), 1);
+  });
+});
+
+describe("deposit with a legacy user account", () => {
+  let stakingToken: Token = null;
+  let ownerStakingATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, LEGACY_OWNER.publicKey);
+
+    ({
+      stakingToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      LEGACY_BASE,
+      TOKEN_DECIMAL,
+      [LEGACY_STAKING_MINT, LEGACY_REWARD_MINT]
+    ));
+    ownerStakingATA = await stakingToken.createAssociatedTokenAccount(
+      LEGACY_OWNER.publicKey
+    );
+    await stakingToken.mintTo(
+      ownerStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should grow and migrate the user account on deposit", async () => {
+    const [userStakingAddress] = await getUserPda(
+      program,
+      farmingPoolAddress,
+      LEGACY_OWNER.publicKey
+    );
+    assert.deepStrictEqual(userStakingAddress, LEGACY_USER);
+    const legacy = await provider.connection.getAccountInfo(LEGACY_USER);
+    assert.ok(legacy.data.length < USER_SIZE);
+
+    await program.methods
+      .depositWithInit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: LEGACY_OWNER.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: ownerStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        user: LEGACY_USER,
+      })
+      .signers([LEGACY_OWNER])
+      .rpc();
+
+    const account = await provider.connection.getAccountInfo(LEGACY_USER);
+    assert.strictEqual(account.data.length, USER_SIZE);
+    const user = await program.account.user.fetch(LEGACY_USER);
+    assert.strictEqual(user.version, USER_VERSION);
+    assert.deepStrictEqual(user.owner, LEGACY_OWNER.publicKey);
+    assert.strictEqual(
+      user.balanceStaked.toNumber(),
+      100 * TOKEN_MULTIPLIER
+    );
+  });
+});
@@ -0,0 +1,188 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  chargeTestPool,
+  createTestPool,
+  createTestUser,
+  expectError,
+  expectEvent,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("early exit penalty", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const setEarlyExitPenalty = (penaltyBps: number, windowSeconds: number) =>
+    program.methods
+      .setEarlyExitPenalty(penaltyBps, new anchor.BN(windowSeconds))
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const stake = (method: "deposit" | "withdraw", amount: number) =>
+    program.methods[method](new anchor.BN(amount * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const claim = () =>
+    program.methods
+      .claim()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount: userRewardATA,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await chargeTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      { rewardToken, pool: farmingPoolAddress, rewardAVault },
+      100_000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should reject a penalty above 100%", async () => {
+    await assert.rejects(
+      setEarlyExitPenalty(10_001, 3600),
+      expectError("InvalidEarlyExitPenalty")
+    );
+  });
+
+  it("should forfeit pending rewards on an early withdrawal", async () => {
+    await setEarlyExitPenalty(5_000, 3600);
+    await stake("deposit", 100);
+    await sleep(3000);
+
+    const event = await expectEvent<any>(program, "EventWithdraw", () =>
+      stake("withdraw", 10)
+    );
+    assert.ok(event.penalty.toNumber() > 0);
+    assert.strictEqual(event.amount.toNumber(), 10 * TOKEN_MULTIPLIER);
+
+    // the principal comes back in full as the reward mint differs
+    const stakingAccount = await stakingToken.getAccountInfo(userStakingATA);
+    assert.strictEqual(
+      stakingAccount.amount.toNumber(),
+      910 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should not let a claim before the withdrawal avoid the penalty", async () => {
+    // the whole stake is in its window, so its rewards stay pending
+    const claimEvent = await expectEvent<any>(program, "EventClaim", claim);
+    assert.strictEqual(claimEvent.amountA.toNumber(), 0);
+    assert.ok(claimEvent.pendingAfter.toNumber() > 0);
+
+    const event = await expectEvent<any>(program, "EventWithdraw", () =>
+      stake("withdraw", 10)
+    );
+    assert.ok(event.penalty.toNumber() > 0);
+  });
+
+  it("should not charge a penalty once the window has passed", async () => {
+    await setEarlyExitPenalty(5_000, 1);
+    await sleep(2000);
+
+    const event = await expectEvent<any>(program, "EventWithdraw", () =>
+      stake("withdraw", 10)
+    );
+    assert.strictEqual(event.penalty.toNumber(), 0);
+    await claim();
+  });
+
+  it("should only charge the penalty on the stake deposited within the window", async () => {
+    await setEarlyExitPenalty(5_000, 3600);
+    await stake("deposit", 10);
+    await sleep(2000);
+
+    // a claim only keeps back the rewards of the stake in its window
+    const claimEvent = await expectEvent<any>(program, "EventClaim", claim);
+    assert.ok(claimEvent.amountA.toNumber() > 0);
+    assert.ok(claimEvent.pendingAfter.toNumber() > 0);
+
+    // 70 of the 80 staked were deposited before the window
+    let event = await expectEvent<any>(program, "EventWithdraw", () =>
+      stake("withdraw", 70)
+    );
+    assert.strictEqual(event.penalty.toNumber(), 0);
+
+    event = await expectEvent<any>(program, "EventWithdraw", () =>
+      stake("withdraw", 10)
+    );
+    assert.ok(event.penalty.toNumber() > 0);
+  });
+});
@@ -0,0 +1,267 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { getClock } from "../clock";
+import {
+  airdrop,
+  createTestPool,
+  createTestUser,
+  expectError,
+  expectEvent,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+const EPOCH_SECONDS = 4;
+
+describe("epochs", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let adminRewardATA: anchor.web3.PublicKey = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const getEpochPda = async (epoch: number) => {
+    const [epochAddress] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("epoch"),
+        farmingPoolAddress.toBuffer(),
+        new anchor.BN(epoch).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return epochAddress;
+  };
+
+  const startEpoch = async (epoch: number, startTime: number, budget: number) =>
+    program.methods
+      .startEpoch(
+        new anchor.BN(epoch),
+        new anchor.BN(startTime),
+        new anchor.BN(startTime + EPOCH_SECONDS),
+        new anchor.BN(budget * TOKEN_MULTIPLIER)
+      )
+      .accounts({
+        epoch: await getEpochPda(epoch),
+        fromA: adminRewardATA,
+        funder: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const finalizeEpoch = async (epoch: number) =>
+    program.methods
+      .finalizeEpoch()
+      .accounts({
+        caller: USER_KEYPAIR.publicKey,
+        epoch: await getEpochPda(epoch),
+        pool: farmingPoolAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const sweepEpoch = async (epoch: number) =>
+    program.methods
+      .sweepEpoch()
+      .accounts({
+        caller: USER_KEYPAIR.publicKey,
+        epoch: await getEpochPda(epoch),
+        pool: farmingPoolAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const claimEpoch = async (epoch: number) =>
+    program.methods
+      .claimEpoch()
+      .accounts({
+        epoch: await getEpochPda(epoch),
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount: userRewardATA,
+        rewardAVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    adminRewardATA = await rewardToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await rewardToken.mintTo(
+      adminRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      10_000 * TOKEN_MULTIPLIER
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await program.methods
+      .deposit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+  });
+
+  it("should start the first epoch and hold its budget", async () => {
+    const clock = await getClock(program.provider.connection);
+    const startTime = clock.info.unixTimestamp + 2;
+    await assert.rejects(
+      startEpoch(2, startTime, 1000),
+      expectError("InvalidEpoch")
+    );
+    await assert.rejects(
+      startEpoch(1, clock.info.unixTimestamp - 100, 1000),
+      expectError("InvalidEpochWindow")
+    );
+
+    const event = await expectEvent<any>(program, "EventStartEpoch", () =>
+      startEpoch(1, startTime, 1000)
+    );
+    assert.strictEqual(event.epoch.toNumber(), 1);
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.epochCount.toNumber(), 1);
+    assert.strictEqual(pool.epochReserved.toNumber(), 1000 * TOKEN_MULTIPLIER);
+    const epoch = await program.account.epoch.fetch(await getEpochPda(1));
+    assert.strictEqual(epoch.budget.toNumber(), 1000 * TOKEN_MULTIPLIER);
+    assert.strictEqual(epoch.endTime.toNumber(), startTime + EPOCH_SECONDS);
+  });
+
+  it("should keep epochs sequential and claims closed until finalized", async () => {
+    const clock = await getClock(program.provider.connection);
+    await assert.rejects(
+      startEpoch(2, clock.info.unixTimestamp + 100, 1000),
+      expectError("EpochNotFinalized")
+    );
+    await assert.rejects(finalizeEpoch(1), expectError("EpochNotEnded"));
+    await assert.rejects(claimEpoch(1), expectError("EpochNotFinalized"));
+  });
+
+  it("should give the only staker the whole budget", async () => {
+    await sleep((EPOCH_SECONDS + 4) * 1000);
+
+    const finalized = await expectEvent<any>(
+      program,
+      "EventFinalizeEpoch",
+      () => finalizeEpoch(1)
+    );
+    // held over the whole epoch
+    assert.strictEqual(
+      finalized.totalStakeSeconds.toString(),
+      (100 * TOKEN_MULTIPLIER * EPOCH_SECONDS).toString()
+    );
+
+    const claimed = await expectEvent<any>(program, "EventClaimEpoch", () =>
+      claimEpoch(1)
+    );
+    assert.strictEqual(claimed.amount.toNumber(), 1000 * TOKEN_MULTIPLIER);
+    assert.strictEqual(
+      claimed.stakeSeconds.toString(),
+      finalized.totalStakeSeconds.toString()
+    );
+    const rewardAccount = await rewardToken.getAccountInfo(userRewardATA);
+    assert.strictEqual(
+      rewardAccount.amount.toNumber(),
+      1000 * TOKEN_MULTIPLIER
+    );
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.epochReserved.toNumber(), 0);
+
+    await assert.rejects(claimEpoch(1), expectError("EpochAlreadyClaimed"));
+  });
+
+  it("should keep the rest of an epoch budget until its claim period is over", async () => {
+    await assert.rejects(sweepEpoch(1), expectError("EpochClaimPeriodNotOver"));
+  });
+
+  it("should not pay pending rewards out of a reserved epoch budget", async () => {
+    const clock = await getClock(program.provider.connection);
+    await startEpoch(2, clock.info.unixTimestamp + 1, 1000);
+    // a budget no tokens back, the vault only holds the epoch budget
+    await program.methods
+      .chargeReward(new anchor.BN(1000 * TOKEN_MULTIPLIER))
+      .accounts({ pool: farmingPoolAddress, signer: ADMIN_KEYPAIR.publicKey })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+    await sleep(2000);
+
+    const claimed = await expectEvent<any>(program, "EventClaim", () =>
+      program.methods
+        .claim()
+        .accounts({
+          owner: USER_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+          rewardAAccount: userRewardATA,
+          rewardAVault,
+          stakingVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          user: userStakingAddress,
+        })
+        .signers([USER_KEYPAIR])
+        .rpc()
+    );
+    assert.ok(claimed.pendingBefore.toNumber() > 0);
+    assert.strictEqual(claimed.amountA.toNumber(), 0);
+    const vault = await rewardToken.getAccountInfo(rewardAVault);
+    assert.strictEqual(vault.amount.toNumber(), 1000 * TOKEN_MULTIPLIER);
+  });
+});
@@ -0,0 +1,102 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, createTestUser, expectEvent } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("indexable events", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    let stakingToken = null;
+    ({
+      stakingToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should report balances and pool totals on deposit", async () => {
+    const amount = new anchor.BN(10 * TOKEN_MULTIPLIER);
+    const deposit = () =>
+      program.methods
+        .deposit(amount)
+        .accounts({
+          owner: USER_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+          stakeFromAccount: userStakingATA,
+          stakingVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          user: userStakingAddress,
+        })
+        .signers([USER_KEYPAIR])
+        .rpc();
+
+    await deposit();
+    const event = await expectEvent<any>(program, "EventDeposit", deposit);
+
+    assert.ok(event.pool.equals(farmingPoolAddress));
+    assert.ok(event.owner.equals(USER_KEYPAIR.publicKey));
+    assert.strictEqual(event.balanceBefore.toString(), amount.toString());
+    assert.strictEqual(
+      event.balanceAfter.toString(),
+      amount.muln(2).toString()
+    );
+    assert.strictEqual(event.totalStaked.toString(), amount.muln(2).toString());
+    assert.ok(event.timestamp.toNumber() > 0);
+  });
+
+  it("should report the actor on pause flag changes", async () => {
+    const event = await expectEvent<any>(program, "EventSetPauseFlags", () =>
+      program.methods
+        .setPauseFlags(1)
+        .accounts({
+          authority: ADMIN_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+        })
+        .signers([ADMIN_KEYPAIR])
+        .rpc()
+    );
+    assert.ok(event.pool.equals(farmingPoolAddress));
+    assert.ok(event.authority.equals(ADMIN_KEYPAIR.publicKey));
+    assert.strictEqual(event.oldPauseFlags, 0);
+    assert.strictEqual(event.newPauseFlags, 1);
+  });
+});
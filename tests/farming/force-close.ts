@@ -0,0 +1,133 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, createTestUser, expectError } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("force close user", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let adminRewardATA: anchor.web3.PublicKey = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const forceCloseUser = (authority: anchor.web3.Keypair = ADMIN_KEYPAIR) =>
+    program.methods
+      .forceCloseUser()
+      .accounts({
+        authority: authority.publicKey,
+        owner: USER_KEYPAIR.publicKey,
+        ownerRewardAAccount: userRewardATA,
+        ownerStakingAccount: userStakingATA,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([authority])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    adminRewardATA = await rewardToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await rewardToken.mintTo(
+      adminRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await program.methods
+      .deposit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+  });
+
+  it("should not force close a user of a pool that was never funded", async () => {
+    await assert.rejects(forceCloseUser(), expectError("ConstraintRaw"));
+  });
+
+  it("should not force close a user within the grace period", async () => {
+    await program.methods
+      .fund(new anchor.BN(10 * TOKEN_MULTIPLIER), null)
+      .accounts({
+        fromA: adminRewardATA,
+        funder: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+    await assert.rejects(forceCloseUser(), expectError("ConstraintRaw"));
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.balanceStaked.toNumber(), 100 * TOKEN_MULTIPLIER);
+  });
+
+  it("should only let the authority force close a user", async () => {
+    await assert.rejects(
+      forceCloseUser(USER_KEYPAIR),
+      expectError("ConstraintHasOne")
+    );
+  });
+});
@@ -0,0 +1,176 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  chargeTestPool,
+  createTestPool,
+  createTestUser,
+  expectError,
+  expectEvent,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("freeze user", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const setFrozen = (frozen: boolean, authority: anchor.web3.Keypair) =>
+    (frozen ? program.methods.freezeUser() : program.methods.unfreezeUser())
+      .accounts({
+        authority: authority.publicKey,
+        pool: farmingPoolAddress,
+        user: userStakingAddress,
+      })
+      .signers([authority])
+      .rpc();
+
+  const stake = (method: "deposit" | "withdraw", amount: number) =>
+    program.methods[method](new anchor.BN(amount * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const claim = () =>
+    program.methods
+      .claim()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount: userRewardATA,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const forceCloseUser = () =>
+    program.methods
+      .forceCloseUser()
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        owner: USER_KEYPAIR.publicKey,
+        ownerRewardAAccount: userRewardATA,
+        ownerStakingAccount: userStakingATA,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await chargeTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      { rewardToken, pool: farmingPoolAddress, rewardAVault },
+      100_000 * TOKEN_MULTIPLIER
+    );
+    await stake("deposit", 100);
+  });
+
+  it("should only let the authority freeze a user", async () => {
+    await assert.rejects(setFrozen(true, USER_KEYPAIR));
+
+    const event = await expectEvent<any>(program, "EventFreezeUser", () =>
+      setFrozen(true, ADMIN_KEYPAIR)
+    );
+    assert.deepStrictEqual(event.owner, USER_KEYPAIR.publicKey);
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.frozen, true);
+  });
+
+  it("should block deposits and claims of a frozen user", async () => {
+    await assert.rejects(stake("deposit", 10), expectError("UserFrozen"));
+    await assert.rejects(claim(), expectError("UserFrozen"));
+  });
+
+  it("should still let a frozen user withdraw", async () => {
+    await stake("withdraw", 40);
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.balanceStaked.toNumber(), 60 * TOKEN_MULTIPLIER);
+  });
+
+  it("should not keep a frozen user from being force closed", async () => {
+    // the grace period after the reward period cannot pass on the test validator, so the force close
+    // only gets as far as the grace period check. The forfeited rewards are covered by test_force_close_rewards.
+    await assert.rejects(forceCloseUser(), (err: anchor.AnchorError) => {
+      assert.strictEqual(err.error.errorCode.code, "ConstraintRaw");
+      assert.strictEqual(err.error.origin, "pool");
+      return true;
+    });
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.frozen, true);
+  });
+
+  it("should lift the restrictions once unfrozen", async () => {
+    await expectEvent<any>(program, "EventUnfreezeUser", () =>
+      setFrozen(false, ADMIN_KEYPAIR)
+    );
+    await stake("deposit", 10);
+    await claim();
+  });
+});
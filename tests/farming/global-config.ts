@@ -0,0 +1,130 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  ensureGlobalConfig,
+  getPoolIndexPda,
+  getProgramDataPda,
+  GLOBAL_CONFIG_ADMIN,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+
+describe("global config and pool index", () => {
+  let globalConfig: anchor.web3.PublicKey = null;
+
+  const setAllowed = (
+    method: "allowStakingMint" | "disallowStakingMint",
+    mint: anchor.web3.PublicKey
+  ) =>
+    program.methods[method](mint)
+      .accounts({
+        admin: GLOBAL_CONFIG_ADMIN.publicKey,
+        globalConfig,
+      })
+      .signers([GLOBAL_CONFIG_ADMIN])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    globalConfig = await ensureGlobalConfig(program);
+  });
+
+  it("should only let the upgrade authority initialize the config", async () => {
+    const [programData] = await getProgramDataPda(program);
+    await assert.rejects(
+      program.methods
+        .initializeGlobalConfig(0, ADMIN_KEYPAIR.publicKey)
+        .accounts({
+          admin: ADMIN_KEYPAIR.publicKey,
+          globalConfig,
+          program: program.programId,
+          programData,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([ADMIN_KEYPAIR])
+        .rpc()
+    );
+    const config = await program.account.globalConfig.fetch(globalConfig);
+    assert.ok(config.admin.equals(GLOBAL_CONFIG_ADMIN.publicKey));
+  });
+
+  it("should list pools sharing a staking mint", async () => {
+    const { stakingToken, pool } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      anchor.web3.Keypair.generate(),
+      TOKEN_DECIMAL
+    );
+    const [poolIndex] = await getPoolIndexPda(program, stakingToken.publicKey);
+    const indexAccount = await program.account.poolIndex.fetch(poolIndex);
+    assert.ok(indexAccount.stakingMint.equals(stakingToken.publicKey));
+    assert.ok(indexAccount.pools.some((p) => p.equals(pool)));
+  });
+
+  it("should only allow listed staking mints once the allowlist is set", async () => {
+    const listedMint = anchor.web3.Keypair.generate().publicKey;
+    await setAllowed("allowStakingMint", listedMint);
+
+    try {
+      await assert.rejects(
+        createTestPool(
+          program,
+          ADMIN_KEYPAIR,
+          anchor.web3.Keypair.generate(),
+          TOKEN_DECIMAL
+        ),
+        (err: anchor.AnchorError) => {
+          assert.strictEqual(err.error.errorCode.code, "StakingMintNotAllowed");
+          return true;
+        }
+      );
+    } finally {
+      // other test files create pools for fresh mints
+      await setAllowed("disallowStakingMint", listedMint);
+    }
+  });
+
+  it("should reject updates from anyone but the admin", async () => {
+    await assert.rejects(
+      program.methods
+        .updateGlobalConfig(ADMIN_KEYPAIR.publicKey, 0, ADMIN_KEYPAIR.publicKey)
+        .accounts({
+          admin: ADMIN_KEYPAIR.publicKey,
+          globalConfig,
+        })
+        .signers([ADMIN_KEYPAIR])
+        .rpc()
+    );
+  });
+
+  it("should reject fees above 100%", async () => {
+    await assert.rejects(
+      program.methods
+        .updateGlobalConfig(
+          GLOBAL_CONFIG_ADMIN.publicKey,
+          10_001,
+          GLOBAL_CONFIG_ADMIN.publicKey
+        )
+        .accounts({
+          admin: GLOBAL_CONFIG_ADMIN.publicKey,
+          globalConfig,
+        })
+        .signers([GLOBAL_CONFIG_ADMIN])
+        .rpc(),
+      (err: anchor.AnchorError) => {
+        assert.strictEqual(err.error.errorCode.code, "InvalidFeeBps");
+        return true;
+      }
+    );
+  });
+});
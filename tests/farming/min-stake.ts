@@ -0,0 +1,152 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  chargeTestPool,
+  createTestPool,
+  createTestUser,
+  expectError,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+const MIN_STAKE_SECONDS = 3;
+
+describe("minimum stake duration", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const claim = () =>
+    program.methods
+      .claim()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount: userRewardATA,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await chargeTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      { rewardToken, pool: farmingPoolAddress, rewardAVault },
+      100_000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should only let the authority set the minimum stake duration", async () => {
+    await assert.rejects(
+      program.methods
+        .setMinStakeSeconds(new anchor.BN(MIN_STAKE_SECONDS))
+        .accounts({
+          authority: USER_KEYPAIR.publicKey,
+          pool: farmingPoolAddress,
+          queuedAction: program.programId,
+        })
+        .signers([USER_KEYPAIR])
+        .rpc()
+    );
+
+    await program.methods
+      .setMinStakeSeconds(new anchor.BN(MIN_STAKE_SECONDS))
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.minStakeSeconds.toNumber(), MIN_STAKE_SECONDS);
+  });
+
+  it("should refuse claims before the deposit matures", async () => {
+    await program.methods
+      .deposit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(
+      user.immatureBalance.toNumber(),
+      100 * TOKEN_MULTIPLIER
+    );
+    assert.ok(user.maturesAt.toNumber() > 0);
+
+    await assert.rejects(claim(), expectError("StakeNotMatured"));
+  });
+
+  it("should accrue and allow claims once the deposit matures", async () => {
+    await sleep((MIN_STAKE_SECONDS + 2) * 1000);
+    await claim();
+
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.immatureBalance.toNumber(), 0);
+    const rewardAccount = await rewardToken.getAccountInfo(userRewardATA);
+    assert.ok(rewardAccount.amount.toNumber() > 0);
+  });
+});
@@ -0,0 +1,170 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  createTestUser,
+  expectError,
+  expectEvent,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("withdrawal outflow limit", () => {
+  let stakingToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const setOutflowLimit = (limitBps: number, windowSeconds: number) =>
+    program.methods
+      .setOutflowLimit(limitBps, new anchor.BN(windowSeconds))
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const withdraw = (amount: number) =>
+    program.methods
+      .withdraw(new anchor.BN(amount * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+    await program.methods
+      .deposit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+  });
+
+  it("should reject a limit above 100%", async () => {
+    await assert.rejects(
+      setOutflowLimit(10_001, 3600),
+      expectError("InvalidOutflowLimit")
+    );
+    await assert.rejects(setOutflowLimit(5_000, 0), expectError("InvalidOutflowLimit"));
+  });
+
+  it("should allow withdrawals within the limit", async () => {
+    await setOutflowLimit(5_000, 3600);
+    await withdraw(40);
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(
+      pool.outflowWindowAmount.toNumber(),
+      40 * TOKEN_MULTIPLIER
+    );
+    assert.strictEqual(
+      pool.outflowWindowBase.toNumber(),
+      100 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should reject a withdrawal over the limit by itself", async () => {
+    await assert.rejects(withdraw(51), expectError("OutflowLimitExceeded"));
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.outflowPausedUntil.toNumber(), 0);
+  });
+
+  it("should pause withdrawals until the end of the window on a withdrawal going over the limit", async () => {
+    const event = await expectEvent<any>(
+      program,
+      "EventOutflowLimitTripped",
+      () => withdraw(20)
+    );
+    assert.deepStrictEqual(event.caller, USER_KEYPAIR.publicKey);
+    assert.strictEqual(event.amount.toNumber(), 20 * TOKEN_MULTIPLIER);
+    assert.strictEqual(event.windowAmount.toNumber(), 40 * TOKEN_MULTIPLIER);
+    assert.strictEqual(event.limitBps, 5_000);
+    assert.strictEqual(
+      event.pausedUntil.toNumber(),
+      event.windowStart.toNumber() + 3600
+    );
+
+    // nothing was transferred
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.balanceStaked.toNumber(), 60 * TOKEN_MULTIPLIER);
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.pauseFlags, 0);
+    assert.strictEqual(
+      pool.outflowWindowAmount.toNumber(),
+      40 * TOKEN_MULTIPLIER
+    );
+    assert.strictEqual(
+      pool.outflowPausedUntil.toNumber(),
+      event.pausedUntil.toNumber()
+    );
+    await assert.rejects(withdraw(1), expectError("WithdrawPaused"));
+  });
+
+  it("should lift the pause when the authority resets the limit", async () => {
+    await setOutflowLimit(5_000, 3600);
+    await withdraw(1);
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.outflowPausedUntil.toNumber(), 0);
+  });
+});
@@ -0,0 +1,138 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, createTestUser, expectError } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+const PAUSE_DEPOSIT = 1 << 0;
+const PAUSE_WITHDRAW = 1 << 1;
+const PAUSE_CLAIM = 1 << 2;
+
+describe("farming with granular pause flags", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const setPauseFlags = (flags: number) =>
+    program.methods
+      .setPauseFlags(flags)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const depositOrWithdraw = (method: "deposit" | "withdraw") =>
+    program.methods[method](new anchor.BN(10 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  const claim = () =>
+    program.methods
+      .claim()
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAAccount: userRewardATA,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    userRewardATA = await rewardToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should block deposits only while the deposit flag is set", async () => {
+    await setPauseFlags(PAUSE_DEPOSIT);
+    await assert.rejects(
+      depositOrWithdraw("deposit"),
+      expectError("DepositPaused")
+    );
+
+    await setPauseFlags(0);
+    await depositOrWithdraw("deposit");
+  });
+
+  it("should block withdrawals and claims independently", async () => {
+    await setPauseFlags(PAUSE_WITHDRAW);
+    await assert.rejects(
+      depositOrWithdraw("withdraw"),
+      expectError("WithdrawPaused")
+    );
+    await claim();
+
+    await setPauseFlags(PAUSE_CLAIM);
+    await assert.rejects(claim(), expectError("ClaimPaused"));
+    await depositOrWithdraw("withdraw");
+
+    await setPauseFlags(0);
+    const poolAccount = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(poolAccount.pauseFlags, 0);
+  });
+
+  it("should reject unknown flags", async () => {
+    await assert.rejects(setPauseFlags(1 << 7), expectError("InvalidPauseFlags"));
+  });
+});
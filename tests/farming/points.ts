@@ -0,0 +1,117 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  createTestUser,
+  returnData,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+const PRECISION = new anchor.BN(1_000_000_000);
+
+describe("points accrual", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let userStakingATA: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  const deposit = (amount: number) =>
+    program.methods
+      .deposit(new anchor.BN(amount * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    let stakingToken = null;
+    ({
+      stakingToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    await program.methods
+      .setPointsRate(PRECISION, true)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      200 * TOKEN_MULTIPLIER
+    );
+    await deposit(100);
+  });
+
+  it("should accrue points without token rewards in points-only mode", async () => {
+    await sleep(3000);
+    const { raw } = await program.methods
+      .getUserPosition()
+      .accounts({ pool: farmingPoolAddress, user: userStakingAddress })
+      .simulate();
+    const position = program.coder.types.decode(
+      "UserPosition",
+      returnData(program, raw)
+    );
+    // 100 tokens at one point per token second
+    assert.ok(position.points.gten(200));
+    assert.strictEqual(position.pending.toNumber(), 0);
+
+    const points = await program.methods
+      .getPoints()
+      .accounts({ pool: farmingPoolAddress, user: userStakingAddress })
+      .simulate();
+    assert.ok(
+      new anchor.BN(returnData(program, points.raw), "le").gte(position.points)
+    );
+  });
+
+  it("should store points on the user when it is updated", async () => {
+    await deposit(10);
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.ok(user.points.gten(200));
+    assert.strictEqual(user.rewardAPerTokenPending.toNumber(), 0);
+  });
+});
@@ -0,0 +1,152 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  getPoolIndexPda,
+  getPoolMetadataPda,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+
+describe("pool metadata", () => {
+  let stakingToken: Token = null;
+  let rewardToken: Token = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+  let poolMetadata: anchor.web3.PublicKey = null;
+
+  const setPoolMetadata = (name: string, tags: string[]) =>
+    program.methods
+      .setPoolMetadata(name, "FARM", "https://example.com/farm.json", tags)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        poolMetadata,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    ({
+      stakingToken,
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    [poolMetadata] = await getPoolMetadataPda(program, farmingPoolAddress);
+  });
+
+  it("should create and update the metadata", async () => {
+    await setPoolMetadata("Test farm", ["stable"]);
+    const created = await program.account.poolMetadata.fetch(poolMetadata);
+    assert.strictEqual(created.name, "Test farm");
+    assert.deepStrictEqual(created.tags, ["stable"]);
+    assert.ok(created.createdAt.toNumber() > 0);
+
+    await setPoolMetadata("Renamed farm", ["stable", "usdc"]);
+    const updated = await program.account.poolMetadata.fetch(poolMetadata);
+    assert.strictEqual(updated.name, "Renamed farm");
+    assert.deepStrictEqual(updated.tags, ["stable", "usdc"]);
+    assert.strictEqual(
+      updated.createdAt.toString(),
+      created.createdAt.toString()
+    );
+  });
+
+  it("should reject oversized metadata", async () => {
+    await assert.rejects(
+      setPoolMetadata("n".repeat(33), []),
+      (err: anchor.AnchorError) => {
+        assert.strictEqual(err.error.errorCode.code, "MetadataTooLong");
+        return true;
+      }
+    );
+  });
+
+  it("should close the metadata along with the pool", async () => {
+    const adminStakingATA = await stakingToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    const adminRewardATA = await rewardToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    await rewardToken.mintTo(
+      adminRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      10 ** TOKEN_DECIMAL
+    );
+    await program.methods
+      .fund(new anchor.BN(10 ** TOKEN_DECIMAL), null)
+      .accounts({
+        fromA: adminRewardATA,
+        funder: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+    // wait for the reward period to end
+    await sleep(12_000);
+    await program.methods
+      .pause()
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+    const [poolIndex] = await getPoolIndexPda(program, stakingToken.publicKey);
+    await program.methods
+      .closePool()
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        poolIndex,
+        poolMetadata,
+        queuedAction: program.programId,
+        refundee: ADMIN_KEYPAIR.publicKey,
+        rewardARefundee: adminRewardATA,
+        rewardAVault,
+        stakingRefundee: adminStakingATA,
+        stakingVault,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+    const connection = program.provider.connection;
+    assert.strictEqual(await connection.getAccountInfo(poolMetadata), null);
+    const indexAccount = await program.account.poolIndex.fetch(poolIndex);
+    assert.ok(!indexAccount.pools.some((p) => p.equals(farmingPoolAddress)));
+    assert.strictEqual(
+      await connection.getAccountInfo(farmingPoolAddress),
+      null
+    );
+  });
+});
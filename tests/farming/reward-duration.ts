@@ -0,0 +1,139 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  createTestPool,
+  expectError,
+  expectEvent,
+  REWARD_DURATION,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const OTHER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("set reward duration", () => {
+  let rewardToken: Token = null;
+  let adminRewardATA: anchor.web3.PublicKey = null;
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let stakingVault: anchor.web3.PublicKey = null;
+  let rewardAVault: anchor.web3.PublicKey = null;
+
+  const setRewardDuration = (
+    duration: number,
+    authority: anchor.web3.Keypair = ADMIN_KEYPAIR
+  ) =>
+    program.methods
+      .setRewardDuration(new anchor.BN(duration))
+      .accounts({
+        authority: authority.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+  const fund = (amount: number) =>
+    program.methods
+      .fund(new anchor.BN(amount * TOKEN_MULTIPLIER), null)
+      .accounts({
+        fromA: adminRewardATA,
+        funder: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        rewardAVault,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, OTHER_KEYPAIR.publicKey);
+
+    ({
+      rewardToken,
+      pool: farmingPoolAddress,
+      stakingVault,
+      rewardAVault,
+    } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+    adminRewardATA = await rewardToken.createAssociatedTokenAccount(
+      ADMIN_KEYPAIR.publicKey
+    );
+    await rewardToken.mintTo(
+      adminRewardATA,
+      ADMIN_KEYPAIR,
+      [],
+      1000 * TOKEN_MULTIPLIER
+    );
+  });
+
+  it("should reject a zero duration and a non-authority caller", async () => {
+    await assert.rejects(setRewardDuration(0), expectError("DurationTooShort"));
+    await assert.rejects(
+      setRewardDuration(100, OTHER_KEYPAIR),
+      expectError("ConstraintHasOne")
+    );
+  });
+
+  it("should stretch a running period and spread its leftover", async () => {
+    await fund(100);
+    const funded = await program.account.pool.fetch(farmingPoolAddress);
+
+    const event = await expectEvent<any>(program, "EventSetRewardDuration", () =>
+      setRewardDuration(100)
+    );
+    assert.strictEqual(event.oldDuration.toNumber(), REWARD_DURATION.toNumber());
+    assert.strictEqual(event.newDuration.toNumber(), 100);
+    // the period restarts from now with the new length
+    assert.strictEqual(
+      event.rewardDurationEnd.toNumber() - event.timestamp.toNumber(),
+      100
+    );
+    // the same leftover over ten times the seconds
+    assert.ok(event.rewardARate.lt(funded.rewardARateU128));
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.currentPeriodDuration.toNumber(), 100);
+    assert.strictEqual(
+      pool.rewardDurationEnd.toNumber(),
+      event.rewardDurationEnd.toNumber()
+    );
+  });
+
+  it("should only change the length of the next period once ended", async () => {
+    await setRewardDuration(2);
+    let pool = await program.account.pool.fetch(farmingPoolAddress);
+    const end = pool.rewardDurationEnd.toNumber();
+    await sleep(4000);
+
+    const event = await expectEvent<any>(program, "EventSetRewardDuration", () =>
+      setRewardDuration(20)
+    );
+    assert.strictEqual(event.rewardDurationEnd.toNumber(), end);
+
+    await fund(10);
+    pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.currentPeriodDuration.toNumber(), 20);
+    assert.strictEqual(
+      pool.rewardDurationEnd.toNumber() - pool.rewardStartTime.toNumber(),
+      20
+    );
+  });
+});
@@ -5,6 +5,8 @@ import { LAMPORTS_PER_SOL } from "@solana/web3.js";
 import assert from "assert";
 import { Farming } from "../../target/types/farming";
 import {
+  ensureGlobalConfig,
+  getPoolIndexPda,
   getPoolPda,
   getRewardAVaultPda,
   // getRewardBVaultPda,
@@ -149,12 +151,16 @@ describe("dual-farming with single reward", () => {
     //   rewardMint,
     //   BASE_KEYPAIR.publicKey
     // );
+    const globalConfig = await ensureGlobalConfig(program);
+    const [poolIndex] = await getPoolIndexPda(program, stakingMint);
     await program.methods
       .initializePool(REWARD_DURATION)
       .accounts({
         authority: ADMIN_KEYPAIR.publicKey,
         base: BASE_KEYPAIR.publicKey,
+        globalConfig,
         pool: farmingPoolAddress,
+        poolIndex,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         rewardAMint: rewardMint,
         // rewardBMint: rewardMint,
@@ -277,7 +283,7 @@ describe("dual-farming with single reward", () => {
     );
 
     await program.methods
-      .fund(FUND_AMOUNT)
+      .fund(FUND_AMOUNT, null)
       .accounts({
         fromA: adminRewardATA,
         funder: ADMIN_KEYPAIR.publicKey,
@@ -445,7 +451,7 @@ describe("dual-farming with single reward", () => {
   //   );
 
   //   await program.methods
-  //     .fund(FUND_AMOUNT)
+  //     .fund(FUND_AMOUNT, null)
   //     .accounts({
   //       fromA: adminRewardATA,
   //       funder: ADMIN_KEYPAIR.publicKey,
@@ -655,7 +661,7 @@ describe("dual-farming with single reward", () => {
     // );
 
     await program.methods
-      .fund(FUND_AMOUNT)
+      .fund(FUND_AMOUNT, null)
       .accounts({
         fromA: adminRewardATA,
         funder: ADMIN_KEYPAIR.publicKey,
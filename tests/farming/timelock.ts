@@ -0,0 +1,143 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, expectError, sleep } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const FUNDER = anchor.web3.Keypair.generate().publicKey;
+const TOKEN_DECIMAL = 6;
+const TIMELOCK_SECONDS = 2;
+
+describe("timelocked admin actions", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+
+  const getQueuedActionPda = async (actionId: number) => {
+    const [queuedAction] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("queued_action"),
+        farmingPoolAddress.toBuffer(),
+        new anchor.BN(actionId).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return queuedAction;
+  };
+
+  const queueAuthorizeFunder = async (
+    actionId: number,
+    funder: anchor.web3.PublicKey
+  ) =>
+    program.methods
+      .queueAction(new anchor.BN(actionId), { authorizeFunder: { funder } })
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: await getQueuedActionPda(actionId),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  const authorizeFunder = (queuedAction: anchor.web3.PublicKey) =>
+    program.methods
+      .authorizeFunder(FUNDER)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    ({ pool: farmingPoolAddress } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+  });
+
+  it("should set the first timelock immediately", async () => {
+    await program.methods
+      .setTimelockSeconds(new anchor.BN(TIMELOCK_SECONDS))
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.timelockSeconds.toNumber(), TIMELOCK_SECONDS);
+  });
+
+  it("should require a queued action once the timelock is set", async () => {
+    await assert.rejects(
+      authorizeFunder(program.programId),
+      expectError("TimelockRequired")
+    );
+  });
+
+  it("should not execute a queued action before its ETA", async () => {
+    await queueAuthorizeFunder(1, FUNDER);
+    const queuedAction = await getQueuedActionPda(1);
+    const account = await program.account.queuedAction.fetch(queuedAction);
+    assert.deepStrictEqual(account.pool, farmingPoolAddress);
+    assert.strictEqual(
+      account.eta.toNumber(),
+      account.queuedAt.toNumber() + TIMELOCK_SECONDS
+    );
+
+    await assert.rejects(
+      authorizeFunder(queuedAction),
+      expectError("TimelockNotExpired")
+    );
+  });
+
+  it("should not execute a queued action with other arguments", async () => {
+    await queueAuthorizeFunder(2, anchor.web3.Keypair.generate().publicKey);
+    await sleep((TIMELOCK_SECONDS + 1) * 1000);
+    await assert.rejects(
+      authorizeFunder(await getQueuedActionPda(2)),
+      expectError("QueuedActionMismatch")
+    );
+  });
+
+  it("should execute a queued action after its ETA and close it", async () => {
+    const queuedAction = await getQueuedActionPda(1);
+    await authorizeFunder(queuedAction);
+
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.ok(pool.funders.some((funder) => funder.equals(FUNDER)));
+    assert.strictEqual(
+      await provider.connection.getAccountInfo(queuedAction),
+      null
+    );
+  });
+
+  it("should cancel a queued action", async () => {
+    const queuedAction = await getQueuedActionPda(2);
+    await program.methods
+      .cancelAction()
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+    assert.strictEqual(
+      await provider.connection.getAccountInfo(queuedAction),
+      null
+    );
+  });
+});
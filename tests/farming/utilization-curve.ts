@@ -0,0 +1,87 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import { airdrop, createTestPool, expectError } from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const PRECISION = new anchor.BN(1_000_000_000);
+const MAX_CURVE_POINTS = 4;
+
+function curve(points: [number, number][]) {
+  const padded = points.map(([totalStaked, rate]) => ({
+    totalStaked: new anchor.BN(totalStaked),
+    rate: new anchor.BN(rate).mul(PRECISION),
+  }));
+  while (padded.length < MAX_CURVE_POINTS) {
+    padded.push({ totalStaked: new anchor.BN(0), rate: new anchor.BN(0) });
+  }
+  return { points: padded, len: points.length };
+}
+
+describe("utilization curve", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+
+  const setUtilizationCurve = (utilizationCurve: any) =>
+    program.methods
+      .setUtilizationCurve(utilizationCurve)
+      .accounts({
+        authority: ADMIN_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        queuedAction: program.programId,
+      })
+      .signers([ADMIN_KEYPAIR])
+      .rpc();
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    ({ pool: farmingPoolAddress } = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    ));
+  });
+
+  it("should reject points out of order", async () => {
+    await assert.rejects(
+      setUtilizationCurve(
+        curve([
+          [1000, 1],
+          [1000, 2],
+        ])
+      ),
+      expectError("InvalidUtilizationCurve")
+    );
+  });
+
+  it("should set a kink curve", async () => {
+    await setUtilizationCurve(
+      curve([
+        [0, 1],
+        [1000, 5],
+        [5000, 6],
+      ])
+    );
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.utilizationCurve.len, 3);
+    // stored in base units
+    assert.strictEqual(
+      pool.utilizationCurve.points[1].totalStaked.toNumber(),
+      1000 * 10 ** TOKEN_DECIMAL
+    );
+  });
+
+  it("should remove the curve", async () => {
+    await setUtilizationCurve(curve([]));
+    const pool = await program.account.pool.fetch(farmingPoolAddress);
+    assert.strictEqual(pool.utilizationCurve.len, 0);
+  });
+});
@@ -1,5 +1,7 @@
 import * as anchor from "@project-serum/anchor";
 import { AnchorError, Program } from "@project-serum/anchor";
+import { MintLayout, Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
 import { Farming } from "../../target/types/farming";
 
 export const REWARD_DURATION = new anchor.BN(10);
@@ -98,3 +100,280 @@ export async function getUserPda(
     program.programId
   );
 }
+
+export async function getGlobalConfigPda(program: anchor.Program<Farming>) {
+  return anchor.web3.PublicKey.findProgramAddress(
+    [Buffer.from("global_config")],
+    program.programId
+  );
+}
+
+export async function getPoolIndexPda(
+  program: anchor.Program<Farming>,
+  stakingMint: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddress(
+    [Buffer.from("pool_index"), stakingMint.toBuffer()],
+    program.programId
+  );
+}
+
+export async function getPoolMetadataPda(
+  program: anchor.Program<Farming>,
+  pool: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddress(
+    [Buffer.from("metadata"), pool.toBuffer()],
+    program.programId
+  );
+}
+
+export const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new anchor.web3.PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
+export async function getProgramDataPda(program: anchor.Program<Farming>) {
+  return anchor.web3.PublicKey.findProgramAddress(
+    [program.programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  );
+}
+
+// Admin of the singleton global config shared by every test file
+export const GLOBAL_CONFIG_ADMIN = anchor.web3.Keypair.generate();
+
+// the provider wallet deploys the program, so only it can initialize the config.
+// It then hands the admin over to GLOBAL_CONFIG_ADMIN.
+export async function ensureGlobalConfig(program: anchor.Program<Farming>) {
+  const [globalConfig] = await getGlobalConfigPda(program);
+  const account = await program.provider.connection.getAccountInfo(
+    globalConfig
+  );
+  if (account == null) {
+    const provider = program.provider as anchor.AnchorProvider;
+    const [programData] = await getProgramDataPda(program);
+    await program.methods
+      .initializeGlobalConfig(0, provider.wallet.publicKey)
+      .accounts({
+        admin: provider.wallet.publicKey,
+        globalConfig,
+        program: program.programId,
+        programData,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .updateGlobalConfig(
+        GLOBAL_CONFIG_ADMIN.publicKey,
+        0,
+        provider.wallet.publicKey
+      )
+      .accounts({
+        admin: provider.wallet.publicKey,
+        globalConfig,
+      })
+      .rpc();
+  }
+  return globalConfig;
+}
+
+export interface TestPool {
+  stakingToken: Token;
+  rewardToken: Token;
+  pool: anchor.web3.PublicKey;
+  stakingVault: anchor.web3.PublicKey;
+  rewardAVault: anchor.web3.PublicKey;
+}
+
+export function sleep(ms: number) {
+  return new Promise((res) => {
+    setTimeout(res, ms);
+  });
+}
+
+// assert.rejects validator matching an Anchor error code
+export function expectError(code: string) {
+  return (err: AnchorError) => {
+    assert.strictEqual(err.error.errorCode.code, code);
+    return true;
+  };
+}
+
+// sends a transaction and resolves with the first `name` event emitted after it
+export async function expectEvent<T>(
+  program: anchor.Program<Farming>,
+  name: string,
+  send: () => Promise<string>
+) {
+  let listener: number = null;
+  const event = new Promise<T>((resolve) => {
+    listener = program.addEventListener(name, (event) => resolve(event));
+  });
+  await send();
+  const received = await event;
+  await program.removeEventListener(listener);
+  return received;
+}
+
+// return data of a simulated view instruction
+export function returnData(
+  program: anchor.Program<Farming>,
+  logs: string[]
+): Buffer {
+  const prefix = `Program return: ${program.programId.toBase58()} `;
+  const log = logs.find((l) => l.startsWith(prefix));
+  return Buffer.from(log.slice(prefix.length), "base64");
+}
+
+export async function airdrop(
+  program: anchor.Program<Farming>,
+  address: anchor.web3.PublicKey,
+  sol = 100
+) {
+  const sig = await program.provider.connection.requestAirdrop(
+    address,
+    sol * anchor.web3.LAMPORTS_PER_SOL
+  );
+  await program.provider.connection.confirmTransaction(sig);
+}
+
+// creates a mint at the address of `mint`, for tests that need known addresses
+export async function createMintAt(
+  program: anchor.Program<Farming>,
+  admin: anchor.web3.Keypair,
+  mint: anchor.web3.Keypair,
+  decimals: number
+) {
+  const connection = program.provider.connection;
+  const tx = new anchor.web3.Transaction().add(
+    anchor.web3.SystemProgram.createAccount({
+      fromPubkey: admin.publicKey,
+      newAccountPubkey: mint.publicKey,
+      lamports: await Token.getMinBalanceRentForExemptMint(connection),
+      space: MintLayout.span,
+      programId: TOKEN_PROGRAM_ID,
+    }),
+    Token.createInitMintInstruction(
+      TOKEN_PROGRAM_ID,
+      mint.publicKey,
+      decimals,
+      admin.publicKey,
+      null
+    )
+  );
+  await anchor.web3.sendAndConfirmTransaction(connection, tx, [admin, mint]);
+  return new Token(connection, mint.publicKey, TOKEN_PROGRAM_ID, admin);
+}
+
+export async function createTestPool(
+  program: anchor.Program<Farming>,
+  admin: anchor.web3.Keypair,
+  base: anchor.web3.Keypair,
+  decimals: number,
+  mints?: [anchor.web3.Keypair, anchor.web3.Keypair]
+): Promise<TestPool> {
+  const [stakingToken, rewardToken] = mints
+    ? [
+        await createMintAt(program, admin, mints[0], decimals),
+        await createMintAt(program, admin, mints[1], decimals),
+      ]
+    : [
+        await Token.createMint(
+          program.provider.connection,
+          admin,
+          admin.publicKey,
+          null,
+          decimals,
+          TOKEN_PROGRAM_ID
+        ),
+        await Token.createMint(
+          program.provider.connection,
+          admin,
+          admin.publicKey,
+          null,
+          decimals,
+          TOKEN_PROGRAM_ID
+        ),
+      ];
+  const [pool] = await getPoolPda(
+    program,
+    stakingToken.publicKey,
+    rewardToken.publicKey,
+    base.publicKey
+  );
+  const [stakingVault] = await getStakingVaultPda(
+    program,
+    stakingToken.publicKey,
+    rewardToken.publicKey,
+    base.publicKey
+  );
+  const [rewardAVault] = await getRewardAVaultPda(
+    program,
+    stakingToken.publicKey,
+    rewardToken.publicKey,
+    base.publicKey
+  );
+
+  const globalConfig = await ensureGlobalConfig(program);
+  const [poolIndex] = await getPoolIndexPda(program, stakingToken.publicKey);
+
+  await program.methods
+    .initializePool(REWARD_DURATION)
+    .accounts({
+      authority: admin.publicKey,
+      base: base.publicKey,
+      globalConfig,
+      pool,
+      poolIndex,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      rewardAMint: rewardToken.publicKey,
+      rewardAVault,
+      stakingMint: stakingToken.publicKey,
+      stakingVault,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .signers([base, admin])
+    .rpc();
+
+  return { stakingToken, rewardToken, pool, stakingVault, rewardAVault };
+}
+
+export async function createTestUser(
+  program: anchor.Program<Farming>,
+  pool: anchor.web3.PublicKey,
+  owner: anchor.web3.Keypair
+) {
+  const [user] = await getUserPda(program, pool, owner.publicKey);
+  await program.methods
+    .createUser()
+    .accounts({
+      owner: owner.publicKey,
+      pool,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      user,
+    })
+    .signers([owner])
+    .rpc();
+  return user;
+}
+
+// mints `amount` into the reward vault and adds it to the reward budget,
+// without restarting the reward period like `fund`
+export async function chargeTestPool(
+  program: anchor.Program<Farming>,
+  admin: anchor.web3.Keypair,
+  {
+    rewardToken,
+    pool,
+    rewardAVault,
+  }: Pick<TestPool, "rewardToken" | "pool" | "rewardAVault">,
+  amount: number
+) {
+  await rewardToken.mintTo(rewardAVault, admin, [], amount);
+  await program.methods
+    .chargeReward(new anchor.BN(amount))
+    .accounts({ pool, signer: admin.publicKey })
+    .signers([admin])
+    .rpc();
+}
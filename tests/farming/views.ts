@@ -0,0 +1,112 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import assert from "assert";
+import { Farming } from "../../target/types/farming";
+import {
+  airdrop,
+  chargeTestPool,
+  createTestPool,
+  createTestUser,
+  returnData,
+  sleep,
+} from "./utils";
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+const program = anchor.workspace.Farming as Program<Farming>;
+
+const BASE_KEYPAIR = anchor.web3.Keypair.generate();
+const ADMIN_KEYPAIR = anchor.web3.Keypair.generate();
+const USER_KEYPAIR = anchor.web3.Keypair.generate();
+const TOKEN_DECIMAL = 6;
+const TOKEN_MULTIPLIER = 10 ** TOKEN_DECIMAL;
+
+describe("read-only views", () => {
+  let farmingPoolAddress: anchor.web3.PublicKey = null;
+  let userStakingAddress: anchor.web3.PublicKey = null;
+
+  before(async () => {
+    await airdrop(program, ADMIN_KEYPAIR.publicKey);
+    await airdrop(program, USER_KEYPAIR.publicKey);
+
+    const testPool = await createTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      BASE_KEYPAIR,
+      TOKEN_DECIMAL
+    );
+    const { stakingToken, stakingVault } = testPool;
+    farmingPoolAddress = testPool.pool;
+    await chargeTestPool(
+      program,
+      ADMIN_KEYPAIR,
+      testPool,
+      100_000 * TOKEN_MULTIPLIER
+    );
+    userStakingAddress = await createTestUser(
+      program,
+      farmingPoolAddress,
+      USER_KEYPAIR
+    );
+    const userStakingATA = await stakingToken.createAssociatedTokenAccount(
+      USER_KEYPAIR.publicKey
+    );
+    await stakingToken.mintTo(
+      userStakingATA,
+      ADMIN_KEYPAIR,
+      [],
+      100 * TOKEN_MULTIPLIER
+    );
+    await program.methods
+      .deposit(new anchor.BN(100 * TOKEN_MULTIPLIER))
+      .accounts({
+        owner: USER_KEYPAIR.publicKey,
+        pool: farmingPoolAddress,
+        stakeFromAccount: userStakingATA,
+        stakingVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: userStakingAddress,
+      })
+      .signers([USER_KEYPAIR])
+      .rpc();
+  });
+
+  it("should return pool stats", async () => {
+    const { raw } = await program.methods
+      .getPoolStats()
+      .accounts({ pool: farmingPoolAddress })
+      .simulate();
+    const stats = program.coder.types.decode(
+      "PoolStats",
+      returnData(program, raw)
+    );
+    assert.strictEqual(
+      stats.totalStaked.toString(),
+      (100 * TOKEN_MULTIPLIER).toString()
+    );
+    assert.strictEqual(stats.userStakeCount, 1);
+  });
+
+  it("should preview pending rewards without mutating", async () => {
+    await sleep(2000);
+    const { raw } = await program.methods
+      .getUserPosition()
+      .accounts({ pool: farmingPoolAddress, user: userStakingAddress })
+      .simulate();
+    const position = program.coder.types.decode(
+      "UserPosition",
+      returnData(program, raw)
+    );
+    assert.strictEqual(
+      position.balanceStaked.toString(),
+      (100 * TOKEN_MULTIPLIER).toString()
+    );
+    assert.ok(position.multiplier.toNumber() >= 1);
+    assert.ok(position.pending.toNumber() > 0);
+
+    const user = await program.account.user.fetch(userStakingAddress);
+    assert.strictEqual(user.rewardAPerTokenPending.toString(), "0");
+  });
+});